@@ -0,0 +1,383 @@
+//! Headings and block ids of a note, which links point into with
+//! `[[Note#Heading]]` and `[[Note#^block-id]]`.
+//!
+//! A heading's section runs up to the next heading of the same or a higher
+//! level. A block is the paragraph or list item ending in ` ^block-id`, or
+//! the paragraph before a line holding only `^block-id`. Nested headings can
+//! be named by their path, as in `[[Note#Chapter#Section]]`. Headings are
+//! compared like note names, so `[text](Note.md#some-heading)` finds
+//! `## Some Heading` too.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::frontmatter::Frontmatter;
+use crate::graph::{GraphData, normalize, resolve_target};
+use crate::links::parse_links;
+use crate::note::read_text;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Number of `#`, 1 to 6
+    pub level: usize,
+    pub text: String,
+    /// Byte range of the heading and its section
+    pub range: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    /// Id without the `^`
+    pub id: String,
+    /// Byte range of the paragraph or list item, id included
+    pub range: Range<usize>,
+}
+
+/// The headings and blocks of a note's text, in order of appearance.
+/// Fenced code blocks and the frontmatter are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Outline {
+    pub headings: Vec<Heading>,
+    pub blocks: Vec<Block>,
+}
+
+/// The part of a note a link points at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Section {
+    /// Byte range in the note's file
+    pub range: Range<usize>,
+    /// First and last line, starting at 1
+    pub line: usize,
+    pub end_line: usize,
+}
+
+/// Level and text of an ATX heading line.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let level = rest.len() - rest.trim_start_matches('#').len();
+    let text = &rest[level..];
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    // a closing sequence of `#` is not part of the text
+    let text = text.trim();
+    let closed = text.trim_end_matches('#');
+    let text = if closed.is_empty() || closed.ends_with([' ', '\t']) {
+        closed.trim_end()
+    } else {
+        text
+    };
+    Some((level, text))
+}
+
+/// The block id at the end of `line` and the text before it.
+fn block_id(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_end();
+    let (before, id) = line.rsplit_once('^')?;
+    let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    (valid && (before.is_empty() || before.ends_with([' ', '\t']))).then(|| (before.trim(), id))
+}
+
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = if digits > 0 {
+        line[digits..].strip_prefix(['.', ')'])
+    } else {
+        line.strip_prefix(['-', '*', '+'])
+    };
+    rest.is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+impl Outline {
+    pub fn parse(text: &str) -> Outline {
+        let (_, body) = Frontmatter::split(text);
+        let mut out = Outline::default();
+        let mut in_fence = false;
+        // start of the paragraph the current line belongs to, and the range
+        // of the last paragraph before a blank line
+        let mut paragraph: Option<usize> = None;
+        let mut previous: Option<Range<usize>> = None;
+        let mut offset = text.len() - body.len();
+        for line in body.split_inclusive('\n') {
+            let start = offset;
+            offset += line.len();
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+                paragraph = None;
+                continue;
+            }
+            if in_fence {
+                continue;
+            }
+            if trimmed.trim_end().is_empty() {
+                if let Some(first) = paragraph.take() {
+                    previous = Some(first..start);
+                }
+                continue;
+            }
+            if let Some((level, title)) = heading(line) {
+                let title = block_id(title).map_or(title, |(before, _)| before);
+                out.headings.push(Heading {
+                    level,
+                    text: title.to_string(),
+                    range: start..text.len(),
+                });
+                paragraph = None;
+                previous = None;
+                continue;
+            }
+            if is_list_item(line) {
+                paragraph = None;
+            }
+            let first = *paragraph.get_or_insert(start);
+            match block_id(line) {
+                // an id on a line of its own names the paragraph before it
+                Some(("", id)) => {
+                    let range = if first < start {
+                        Some(first..start)
+                    } else {
+                        previous.clone()
+                    };
+                    if let Some(range) = range {
+                        out.blocks.push(Block {
+                            id: id.to_string(),
+                            range,
+                        });
+                    }
+                }
+                Some((_, id)) => out.blocks.push(Block {
+                    id: id.to_string(),
+                    range: first..offset,
+                }),
+                None => {}
+            }
+            if is_list_item(line) {
+                // every list item is a block of its own
+                paragraph = None;
+                previous = Some(start..offset);
+            }
+        }
+        // a section ends where the next heading of the same or a higher
+        // level starts
+        for k in 0..out.headings.len() {
+            let level = out.headings[k].level;
+            if let Some(next) = out.headings[k + 1..].iter().find(|h| h.level <= level) {
+                out.headings[k].range.end = next.range.start;
+            }
+        }
+        out
+    }
+
+    /// The range `anchor` names: `^id` for a block, else a heading or a path
+    /// of nested headings separated by `#`.
+    pub fn resolve(&self, anchor: &str) -> Option<Range<usize>> {
+        let anchor = anchor.trim();
+        if let Some(id) = anchor.strip_prefix('^') {
+            return self
+                .blocks
+                .iter()
+                .find(|b| b.id.eq_ignore_ascii_case(id))
+                .map(|b| b.range.clone());
+        }
+        let mut scope: Option<Range<usize>> = None;
+        for part in anchor.split('#') {
+            let wanted = normalize(part);
+            if wanted.is_empty() {
+                return None;
+            }
+            let inside = |h: &&Heading| match &scope {
+                Some(outer) => h.range.start > outer.start && h.range.end <= outer.end,
+                None => true,
+            };
+            let found = self
+                .headings
+                .iter()
+                .filter(inside)
+                .find(|h| normalize(&h.text) == wanted)?;
+            scope = Some(found.range.clone());
+        }
+        scope
+    }
+}
+
+/// The section of `text` that `anchor` names, with its lines.
+pub fn find_section(text: &str, anchor: &str) -> Option<Section> {
+    let range = Outline::parse(text).resolve(anchor)?;
+    let line = text[..range.start].matches('\n').count() + 1;
+    let end_line = line
+        + text[range.clone()]
+            .trim_end_matches('\n')
+            .matches('\n')
+            .count();
+    Some(Section {
+        range,
+        line,
+        end_line,
+    })
+}
+
+/// The section of node `i` that `anchor` names, read from its text file.
+pub fn resolve_anchor(data: &GraphData, i: usize, anchor: &str) -> Result<(PathBuf, Section)> {
+    let node = &data.graph.nodes[i];
+    let not_found = || Error::AnchorNotFound {
+        note: node.name.clone(),
+        anchor: anchor.to_string(),
+    };
+    let path = node.text_file().ok_or_else(not_found)?;
+    let text = read_text(path)?;
+    let section = find_section(&text, anchor).ok_or_else(not_found)?;
+    Ok((path.clone(), section))
+}
+
+/// A link to a heading or block that does not exist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DanglingAnchor {
+    /// File with the link, relative to the vault
+    pub path: PathBuf,
+    /// Line of the link, starting at 1
+    pub line: usize,
+    /// Name of the note linked to
+    pub target: String,
+    pub anchor: String,
+}
+
+/// Links in the vault to headings or blocks that do not exist, sorted by
+/// file and line. Links to notes that do not exist are not included.
+pub fn dangling_anchors(data: &GraphData) -> Result<Vec<DanglingAnchor>> {
+    // outline of each linked node's text file, read once
+    let mut outlines: HashMap<usize, Option<Outline>> = HashMap::new();
+    let mut out = Vec::new();
+    for (i, node) in data.graph.nodes.iter().enumerate() {
+        for path in &node.paths {
+            let bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+            let Ok(text) = String::from_utf8(bytes) else {
+                continue;
+            };
+            let (_, body) = Frontmatter::split(&text);
+            let skipped = text.len() - body.len();
+            for link in parse_links(body) {
+                let Some(anchor) = link.anchor.filter(|a| !a.is_empty()) else {
+                    continue;
+                };
+                // anchors into other formats, like `#page=3` of a PDF, are
+                // not headings
+                let extension = link.target.rsplit_once('.').map(|(_, ext)| ext);
+                if extension.is_some_and(|ext| !ext.eq_ignore_ascii_case("md")) {
+                    continue;
+                }
+                let j = if link.target.is_empty() {
+                    i
+                } else {
+                    match resolve_target(data, &link.target, node.folder()) {
+                        Some((j, _)) => j,
+                        None => continue,
+                    }
+                };
+                let outline = outlines.entry(j).or_insert_with(|| {
+                    let file = data.graph.nodes[j].text_file()?;
+                    fs::read_to_string(file).ok().map(|t| Outline::parse(&t))
+                });
+                if outline
+                    .as_ref()
+                    .is_some_and(|o| o.resolve(&anchor).is_some())
+                {
+                    continue;
+                }
+                let start = skipped + link.span.start;
+                out.push(DanglingAnchor {
+                    path: path.strip_prefix(data.root()).unwrap_or(path).to_path_buf(),
+                    line: text[..start].matches('\n').count() + 1,
+                    target: data.graph.nodes[j].name.clone(),
+                    anchor,
+                });
+            }
+        }
+    }
+    out.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outline, dangling_anchors, find_section, resolve_anchor};
+    use crate::graph::load_from;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    const NOTE: &str = "---\ntitle: Guide\n---\n# Guide\nIntro.\n\n## Setup ##\nInstall it.\nThen run it. ^run\n\n### Linux\nUse apt.\n```\n# not a heading\n```\n## Usage\n- one\n- two ^second\n\n| a |\n^table\n";
+
+    #[test]
+    fn parses_headings_and_blocks() {
+        let outline = Outline::parse(NOTE);
+        let headings: Vec<(usize, &str)> = outline
+            .headings
+            .iter()
+            .map(|h| (h.level, h.text.as_str()))
+            .collect();
+        assert_eq!(
+            headings,
+            [(1, "Guide"), (2, "Setup"), (3, "Linux"), (2, "Usage")]
+        );
+        let text = |range: std::ops::Range<usize>| &NOTE[range];
+        assert!(text(outline.resolve("setup").unwrap()).ends_with("```\n"));
+        assert_eq!(
+            text(outline.resolve("Guide#Setup#Linux").unwrap()),
+            "### Linux\nUse apt.\n```\n# not a heading\n```\n"
+        );
+        assert_eq!(outline.resolve("Usage#Linux"), None);
+        assert_eq!(outline.resolve("not a heading"), None);
+        assert_eq!(
+            text(outline.resolve("^run").unwrap()),
+            "Install it.\nThen run it. ^run\n"
+        );
+        assert_eq!(text(outline.resolve("^second").unwrap()), "- two ^second\n");
+        assert_eq!(text(outline.resolve("^table").unwrap()), "| a |\n");
+        assert_eq!(outline.resolve("^missing"), None);
+
+        let section = find_section(NOTE, "Linux").unwrap();
+        assert_eq!((section.line, section.end_line), (11, 15));
+        assert_eq!(find_section(NOTE, "^RUN").unwrap().line, 8);
+    }
+
+    #[test]
+    fn resolves_and_reports_anchors() {
+        let root = TempDir::new("anchors");
+        fs::write(root.join("Guide.md"), NOTE).unwrap();
+        fs::write(
+            root.join("Plan.md"),
+            "See [[Guide#Linux]] and [[Guide#^run]].\n[[Guide#Windows]]\n[x](Guide.md#setup)\n[[#Nowhere]] [[Missing#X]] [[Guide.pdf#page=2]]\n",
+        )
+        .unwrap();
+
+        let data = load_from(&root).unwrap();
+        let guide = data.find("Guide").unwrap();
+        let (path, section) = resolve_anchor(&data, guide, "Setup#Linux").unwrap();
+        assert_eq!(path, root.join("Guide.md"));
+        assert_eq!(section.line, 11);
+        assert!(resolve_anchor(&data, guide, "Windows").is_err());
+
+        let dangling: Vec<(usize, String, String)> = dangling_anchors(&data)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.line, d.target, d.anchor))
+            .collect();
+        assert_eq!(
+            dangling,
+            [
+                (2, "Guide".into(), "Windows".into()),
+                (4, "Plan".into(), "Nowhere".into())
+            ]
+        );
+    }
+}
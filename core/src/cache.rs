@@ -0,0 +1,83 @@
+//! On-disk copy of the graph index, kept in `.notes/index` inside the vault.
+//!
+//! The cache only saves work: anything wrong with it (missing, unreadable,
+//! corrupt or written by another version) makes [`read`] return `None` and the
+//! index is rebuilt from the notes themselves.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::graph::{Edge, FileState, LinkMode, Node};
+use crate::vault::STATE_DIR;
+
+/// Bumped whenever the layout of [`Snapshot`] or the meaning of its contents
+/// changes.
+const VERSION: u32 = 3;
+
+const FILE_NAME: &str = "index";
+
+/// Everything needed to restore a `GraphData` without reading the notes.
+/// Paths are relative to the vault so that it can be moved.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    pub version: u32,
+    pub mode: LinkMode,
+    pub nodes: Vec<Node>,
+    pub contents: Vec<String>,
+    pub explicit: Vec<Vec<String>>,
+    pub outgoing: Vec<Vec<Edge>>,
+    pub files: Vec<(PathBuf, FileState)>,
+}
+
+impl Snapshot {
+    pub fn new(mode: LinkMode) -> Self {
+        Snapshot {
+            version: VERSION,
+            mode,
+            nodes: Vec::new(),
+            contents: Vec::new(),
+            explicit: Vec::new(),
+            outgoing: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Whether the per-node lists line up and every edge points at a node.
+    fn is_consistent(&self) -> bool {
+        let n = self.nodes.len();
+        self.contents.len() == n
+            && self.explicit.len() == n
+            && self.outgoing.len() == n
+            && self
+                .outgoing
+                .iter()
+                .enumerate()
+                .all(|(i, edges)| edges.iter().all(|e| e.from == i && e.to < n))
+    }
+}
+
+pub(crate) fn path(root: &Path) -> PathBuf {
+    root.join(STATE_DIR).join(FILE_NAME)
+}
+
+/// Read the cache of the vault at `root`, if there is a usable one.
+pub(crate) fn read(root: &Path) -> Option<Snapshot> {
+    let bytes = fs::read(path(root)).ok()?;
+    let snapshot: Snapshot = serde_json::from_slice(&bytes).ok()?;
+    (snapshot.version == VERSION && snapshot.is_consistent()).then_some(snapshot)
+}
+
+/// Replace the cache of the vault at `root`. The new index is written next to
+/// the old one and renamed over it, so readers never see half a file.
+pub(crate) fn write(root: &Path, snapshot: &Snapshot) -> Result<()> {
+    let path = path(root);
+    let dir = root.join(STATE_DIR);
+    fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+    let tmp = path.with_extension("tmp");
+    let bytes = serde_json::to_vec(snapshot).expect("snapshot is always serializable");
+    fs::write(&tmp, bytes).map_err(|e| Error::io(&tmp, e))?;
+    fs::rename(&tmp, &path).map_err(|e| Error::io(&path, e))
+}
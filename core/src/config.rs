@@ -0,0 +1,344 @@
+//! Settings of the opened vault and of the user.
+//!
+//! Each vault may carry its settings in `.notes/config.toml`. Like the vault
+//! directory itself, the configuration is set once per process by whoever
+//! opens the vault and read everywhere else through [`config`].
+//!
+//! Settings that are needed before a vault is open, such as which vault to
+//! open, live in the [`UserConfig`] instead.
+
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+use crate::editor::DEFAULT_EDITOR;
+use crate::error::{Error, Result};
+use crate::graph::LinkMode;
+use crate::note::read_text;
+use crate::periodic::valid_format;
+use crate::vault::{DEFAULT_IGNORE, STATE_DIR};
+
+/// Version of the configuration format understood by this build.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Name of the configuration file, both in a vault's state folder and in
+/// the user's configuration folder.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Application id the GUI registers under unless configured otherwise.
+pub const DEFAULT_APP_ID: &str = "com.example.notes";
+
+/// Settings read from a vault's TOML file. Keys that are left out keep their
+/// default value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Format version the file was written for
+    pub version: u32,
+    /// Which kinds of links the graph is built from
+    pub link_mode: LinkMode,
+    /// Glob patterns of file and folder names skipped when walking the
+    /// vault, e.g. `*.tmp`
+    pub ignore: Vec<String>,
+    /// Editor used when neither `$VISUAL` nor `$EDITOR` is set
+    pub editor: String,
+    /// Extension of newly created notes, without the dot
+    pub default_format: String,
+    /// Whether Markdown only counts as a note's primary format when the note
+    /// has no file in another format
+    pub markdown_last: bool,
+    /// Folder holding note templates, relative to the vault
+    pub templates_dir: PathBuf,
+    /// Layout of the graph view
+    pub graph: Physics,
+    /// Where daily, weekly and monthly notes are kept and how they are named
+    pub periodic: Periodic,
+}
+
+/// Constants of the force simulation that lays out the graph view.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Physics {
+    /// Milliseconds between two steps of the simulation
+    pub tick_ms: u64,
+    /// Strength with which every two nodes push each other apart
+    pub repulsion: f64,
+    /// Stiffness of the springs along links
+    pub spring: f64,
+    /// Length at which a spring neither pulls nor pushes
+    pub spring_length: f64,
+    /// Share of its velocity a node keeps from one step to the next
+    pub damping: f64,
+    /// How far a node moves per unit of velocity in one step
+    pub step: f64,
+}
+
+/// Folder and names of periodic notes. Names are strftime formats of the
+/// first day of the period.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Periodic {
+    /// Folder of periodic notes, relative to the vault
+    pub folder: PathBuf,
+    pub daily: String,
+    pub weekly: String,
+    pub monthly: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            link_mode: LinkMode::default(),
+            ignore: DEFAULT_IGNORE.iter().map(|s| s.to_string()).collect(),
+            editor: DEFAULT_EDITOR.to_string(),
+            default_format: "md".to_string(),
+            markdown_last: true,
+            templates_dir: Path::new(STATE_DIR).join("templates"),
+            graph: Physics::default(),
+            periodic: Periodic::default(),
+        }
+    }
+}
+
+impl Default for Periodic {
+    fn default() -> Self {
+        Periodic {
+            folder: PathBuf::from("journal"),
+            daily: "%Y-%m-%d".to_string(),
+            weekly: "%G-W%V".to_string(),
+            monthly: "%Y-%m".to_string(),
+        }
+    }
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Physics {
+            tick_ms: 16,
+            repulsion: 2000.0,
+            spring: 0.01,
+            spring_length: 100.0,
+            damping: 0.85,
+            step: 0.1,
+        }
+    }
+}
+
+impl Config {
+    /// Read settings from the TOML file at `path`. Besides the settings,
+    /// returns a warning for every key that is not a known setting.
+    pub fn load(path: &Path) -> Result<(Config, Vec<String>)> {
+        let text = read_text(path)?;
+        Config::parse(&text).map_err(|message| Error::config(path, &message))
+    }
+
+    /// Read the settings of the vault at `root` from `.notes/config.toml`,
+    /// or use the defaults if the vault has no such file.
+    pub fn for_vault(root: &Path) -> Result<(Config, Vec<String>)> {
+        let path = root.join(STATE_DIR).join(CONFIG_FILE);
+        if !path.is_file() {
+            return Ok((Config::default(), Vec::new()));
+        }
+        Config::load(&path)
+    }
+
+    fn parse(text: &str) -> std::result::Result<(Config, Vec<String>), String> {
+        let table: toml::Table = toml::from_str(text).map_err(|e| e.message().to_string())?;
+        let config: Config = toml::from_str(text).map_err(|e| e.message().to_string())?;
+        let known = toml::Table::try_from(Config::default()).expect("defaults serialize");
+        let mut warnings = Vec::new();
+        unknown_keys(&table, &known, "", &mut warnings);
+        Ok((config.validated()?, warnings))
+    }
+
+    /// Check the values that TOML types alone cannot restrict.
+    fn validated(mut self) -> std::result::Result<Config, String> {
+        if self.version == 0 || self.version > CONFIG_VERSION {
+            return Err(format!(
+                "version {} is not supported, expected {}",
+                self.version, CONFIG_VERSION
+            ));
+        }
+        self.default_format = self
+            .default_format
+            .trim_start_matches('.')
+            .to_ascii_lowercase();
+        if self.default_format.is_empty()
+            || !self.default_format.chars().all(|c| c.is_alphanumeric())
+        {
+            return Err("default_format must be a file extension such as \"md\"".into());
+        }
+        if self.ignore.iter().any(|pattern| pattern.is_empty()) {
+            return Err("ignore patterns must not be empty".into());
+        }
+        if !inside_vault(&self.templates_dir) {
+            return Err("templates_dir must be a folder inside the vault".into());
+        }
+        let periodic = &self.periodic;
+        if !inside_vault(&periodic.folder) {
+            return Err("periodic.folder must be inside the vault".into());
+        }
+        let formats = [
+            ("daily", &periodic.daily),
+            ("weekly", &periodic.weekly),
+            ("monthly", &periodic.monthly),
+        ];
+        for (name, format) in formats {
+            if format.is_empty() || format.contains('/') || !valid_format(format) {
+                return Err(format!(
+                    "periodic.{} must be a date format such as \"%Y-%m-%d\"",
+                    name
+                ));
+            }
+        }
+        let physics = &self.graph;
+        if physics.tick_ms == 0 {
+            return Err("graph.tick_ms must be at least 1".into());
+        }
+        let constants = [
+            ("repulsion", physics.repulsion),
+            ("spring", physics.spring),
+            ("spring_length", physics.spring_length),
+            ("damping", physics.damping),
+            ("step", physics.step),
+        ];
+        for (name, value) in constants {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("graph.{} must be a number of at least 0", name));
+            }
+        }
+        if physics.damping > 1.0 {
+            return Err("graph.damping must be at most 1".into());
+        }
+        Ok(self)
+    }
+}
+
+/// Whether the relative `path` stays inside the vault: no root, drive or
+/// `..` in it.
+fn inside_vault(path: &Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Collect the keys of `table` that `known` does not have, descending into
+/// tables both have.
+fn unknown_keys(table: &toml::Table, known: &toml::Table, prefix: &str, out: &mut Vec<String>) {
+    for (key, value) in table {
+        let name = format!("{}{}", prefix, key);
+        match (value, known.get(key)) {
+            (_, None) => out.push(format!("unknown key '{}'", name)),
+            (toml::Value::Table(table), Some(toml::Value::Table(known))) => {
+                unknown_keys(table, known, &format!("{}.", name), out)
+            }
+            _ => {}
+        }
+    }
+}
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+pub fn set_config(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Settings of the user rather than of a vault, read from
+/// [`user_config_path`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UserConfig {
+    /// Vault used when no other one is given or found
+    pub vault: Option<PathBuf>,
+    /// Application id of the GUI
+    pub app_id: String,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            vault: None,
+            app_id: DEFAULT_APP_ID.to_string(),
+        }
+    }
+}
+
+impl UserConfig {
+    /// Read the user's settings, or use the defaults if there is no file.
+    pub fn load() -> Result<UserConfig> {
+        match user_config_path() {
+            Some(path) => UserConfig::load_from(&path),
+            None => Ok(UserConfig::default()),
+        }
+    }
+
+    pub(crate) fn load_from(path: &Path) -> Result<UserConfig> {
+        if !path.is_file() {
+            return Ok(UserConfig::default());
+        }
+        let text = read_text(path)?;
+        toml::from_str(&text).map_err(|e| Error::config(path, e.message()))
+    }
+}
+
+/// `$XDG_CONFIG_HOME/notes/config.toml`, falling back to
+/// `~/.config/notes/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("notes").join(CONFIG_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::graph::LinkMode;
+
+    #[test]
+    fn parse_and_validate() {
+        let (config, warnings) = Config::parse(
+            "version = 1\n\
+             link_mode = \"explicit\"\n\
+             default_format = \".Org\"\n\
+             colour = \"red\"\n\
+             [graph]\n\
+             tick_ms = 33\n\
+             gravity = 1.0\n",
+        )
+        .unwrap();
+        assert_eq!(config.link_mode, LinkMode::Explicit);
+        assert_eq!(config.default_format, "org");
+        assert_eq!(config.graph.tick_ms, 33);
+        assert_eq!(config.graph.repulsion, 2000.0);
+        assert_eq!(config.editor, "nvim");
+        assert_eq!(
+            warnings,
+            ["unknown key 'colour'", "unknown key 'graph.gravity'"]
+        );
+
+        for bad in [
+            "version = 2",
+            "default_format = \"\"",
+            "templates_dir = \"/etc\"",
+            "templates_dir = \"../elsewhere\"",
+            "templates_dir = \"templates/../../x\"",
+            "[graph]\ntick_ms = 0",
+            "[graph]\ndamping = 1.5",
+            "[graph]\nspring = -1.0",
+            "ignore = \"target\"",
+            "[periodic]\nfolder = \"../out\"",
+            "[periodic]\ndaily = \"%Y/%m/%d\"",
+            "[periodic]\nweekly = \"%Q\"",
+        ] {
+            assert!(Config::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Opening notes in the user's text editor and creating them from templates.
+//!
+//! The editor is taken from `$VISUAL`, then `$EDITOR`, then the `editor`
+//! setting of the [`Config`](crate::config::Config). The command may carry
+//! arguments, e.g. `code --wait`.
+//!
+//! Templates are files in the configured templates folder, named by their
+//! stem. A `{{name}}` placeholder in a template is filled in when a note is
+//! created from it: `title`, `date`, `time` and `uuid` are built in, and any
+//! other name is a variable whose value the caller asks the user for.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::{DateTime, Local};
+
+use crate::config::config;
+use crate::error::{Error, Result};
+use crate::frontmatter::Frontmatter;
+use crate::note::{Note, read_text, vault_dir};
+use crate::periodic::valid_format;
+
+/// Editor used when neither `$VISUAL` nor `$EDITOR` is set and the
+/// configuration does not name one.
+pub const DEFAULT_EDITOR: &str = "nvim";
+
+/// The editor command split into program and arguments.
+pub fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .chain(std::iter::once(config().editor.clone()))
+        .map(|cmd| cmd.split_whitespace().map(String::from).collect::<Vec<_>>())
+        .find(|words| !words.is_empty())
+        .unwrap_or_else(|| vec![DEFAULT_EDITOR.to_string()])
+}
+
+/// The editor command with `path` appended, ready to be spawned.
+pub fn editor_argv(path: &Path) -> Vec<String> {
+    let mut argv = editor_command();
+    argv.push(path.to_string_lossy().into_owned());
+    argv
+}
+
+/// Edit `path` and wait for the editor to exit.
+pub fn edit(path: &Path) -> Result<()> {
+    let argv = editor_argv(path);
+    let status = Command::new(&argv[0])
+        .args(&argv[1..])
+        .status()
+        .map_err(|e| Error::editor(&argv[0], &e.to_string()))?;
+    if !status.success() {
+        return Err(Error::editor(&argv[0], &format!("exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// Name of the template new notes start from unless another one is chosen.
+pub const DEFAULT_TEMPLATE: &str = "default";
+
+/// Placeholders filled in without asking.
+const BUILTIN_VARIABLES: &[&str] = &["title", "date", "time", "uuid"];
+
+/// Template new notes start from: `default.<format>` in the configured
+/// templates folder, `.notes/templates` unless set otherwise.
+pub fn default_template_path() -> PathBuf {
+    let config = config();
+    templates_dir().join(format!("{}.{}", DEFAULT_TEMPLATE, config.default_format))
+}
+
+fn templates_dir() -> PathBuf {
+    vault_dir().join(&config().templates_dir)
+}
+
+/// Names of the templates in the templates folder, sorted.
+pub fn list_templates() -> Result<Vec<String>> {
+    let dir = templates_dir();
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::io(&dir, e)),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| Error::io(&dir, e))?.path();
+        if path.is_file()
+            && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+        {
+            names.push(stem.to_string());
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// A note template read from the templates folder.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub path: PathBuf,
+    pub text: String,
+}
+
+impl Template {
+    /// Read the template called `name`, preferring the file in the default
+    /// format if there are several.
+    pub fn load(name: &str) -> Result<Template> {
+        let dir = templates_dir();
+        let mut candidates = vec![dir.join(format!("{}.{}", name, config().default_format))];
+        if let Ok(entries) = fs::read_dir(&dir) {
+            let mut others: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| {
+                    path.file_stem().and_then(|s| s.to_str()) == Some(name)
+                        || path.file_name().and_then(|s| s.to_str()) == Some(name)
+                })
+                .collect();
+            others.sort();
+            candidates.extend(others);
+        }
+        let path = candidates
+            .into_iter()
+            .find(|path| path.is_file())
+            .ok_or_else(|| Error::TemplateNotFound(name.to_string()))?;
+        let text = read_text(&path)?;
+        Ok(Template {
+            name: name.to_string(),
+            path,
+            text,
+        })
+    }
+
+    /// The default template, if the vault has one.
+    pub fn default_template() -> Result<Option<Template>> {
+        match Template::load(DEFAULT_TEMPLATE) {
+            Ok(template) => Ok(Some(template)),
+            Err(Error::TemplateNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Names of the placeholders other than the built-in ones, in order of
+    /// first appearance.
+    pub fn variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for Placeholder { name, .. } in placeholders(&self.text) {
+            if !BUILTIN_VARIABLES.contains(&name) && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    /// The template text with every placeholder filled in. Variables without
+    /// a value in `values` are left empty.
+    ///
+    /// `date` and `time` take an optional strftime format after a colon, as
+    /// in `{{date:%d.%m.%Y}}`.
+    pub fn render(&self, title: &str, values: &HashMap<String, String>) -> String {
+        self.render_at(title, values, Local::now())
+    }
+
+    /// Like [`Template::render`], with `now` as the current date and time.
+    pub fn render_at(
+        &self,
+        title: &str,
+        values: &HashMap<String, String>,
+        now: DateTime<Local>,
+    ) -> String {
+        let mut out = String::new();
+        let mut last = 0;
+        for placeholder in placeholders(&self.text) {
+            out.push_str(&self.text[last..placeholder.span.start]);
+            let format = placeholder.format;
+            match placeholder.name {
+                "title" => out.push_str(title),
+                "date" | "time" => {
+                    let default = if placeholder.name == "date" {
+                        "%Y-%m-%d"
+                    } else {
+                        "%H:%M"
+                    };
+                    match format.unwrap_or(default) {
+                        format if valid_format(format) => {
+                            out.push_str(&now.format(format).to_string())
+                        }
+                        // left as it is, so the mistake shows in the note
+                        _ => out.push_str(&self.text[placeholder.span.clone()]),
+                    }
+                }
+                "uuid" => out.push_str(&uuid::Uuid::new_v4().to_string()),
+                name => out.push_str(values.get(name).map_or("", String::as_str)),
+            }
+            last = placeholder.span.end;
+        }
+        out.push_str(&self.text[last..]);
+        out
+    }
+}
+
+/// A `{{name}}` or `{{name:format}}` in a template.
+struct Placeholder<'a> {
+    span: Range<usize>,
+    name: &'a str,
+    format: Option<&'a str>,
+}
+
+/// The placeholders in `text`, in order. Braces around anything but a name,
+/// e.g. in a code sample, are not a placeholder.
+fn placeholders(text: &str) -> Vec<Placeholder<'_>> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = text[pos..].find("{{") {
+        let start = pos + found;
+        pos = start + 2;
+        let Some(len) = text[pos..].find("}}") else {
+            break;
+        };
+        let inner = &text[pos..pos + len];
+        let (name, format) = match inner.split_once(':') {
+            Some((name, format)) => (name.trim(), Some(format.trim())),
+            None => (inner.trim(), None),
+        };
+        let is_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if is_name {
+            pos += len + 2;
+            out.push(Placeholder {
+                span: start..pos,
+                name,
+                format,
+            });
+        }
+    }
+    out
+}
+
+/// Create a note for `title` from the default template, or empty if there is
+/// no template.
+pub fn create_from_template(title: &str) -> Result<Note> {
+    let template = Template::default_template()?;
+    create_note(title, template.as_ref(), &HashMap::new())
+}
+
+/// Create a note for `title`, filled in from `template` with the given
+/// values of its variables, or empty without a template. A file already at
+/// the note's path is left alone and reported as [`Error::NoteExists`].
+pub fn create_note(
+    title: &str,
+    template: Option<&Template>,
+    values: &HashMap<String, String>,
+) -> Result<Note> {
+    let text = template
+        .map(|t| t.render(title, values))
+        .unwrap_or_default();
+    let note = note_from_text(title, &text);
+    note.create()?;
+    Ok(note)
+}
+
+/// An unsaved note for `title` in the vault root with `text`, frontmatter
+/// included, as its contents.
+pub(crate) fn note_from_text(title: &str, text: &str) -> Note {
+    let (frontmatter, body) = Frontmatter::split(text);
+    let mut note = Note::new(title.to_string(), body.to_string(), None);
+    if let Some(fm) = &frontmatter {
+        note.aliases = fm.get_list("aliases");
+        note.tags = fm.get_list("tags");
+    }
+    note.frontmatter = frontmatter;
+    note
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Template;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    #[test]
+    fn fills_in_placeholders() {
+        let template = Template {
+            name: "meeting".into(),
+            path: PathBuf::from("meeting.md"),
+            text: "# {{title}} on {{date}}\n{{ date:%Y }} {{time}} {{uuid}}\n\
+                   With {{attendees}}, about {{topic}} and {{attendees}}.\n\
+                   `{{ not a name }}` {{date:%Q}} {{\n"
+                .into(),
+        };
+        assert_eq!(template.variables(), ["attendees", "topic"]);
+
+        let values = HashMap::from([("attendees".to_string(), "Ana".to_string())]);
+        let text = template.render("Standup", &values);
+        let lines: Vec<&str> = text.lines().collect();
+        let today = chrono::Local::now();
+        assert_eq!(
+            lines[0],
+            format!("# Standup on {}", today.format("%Y-%m-%d"))
+        );
+        let words: Vec<&str> = lines[1].split(' ').collect();
+        assert_eq!(words[0], today.format("%Y").to_string());
+        assert_eq!(words[1].len(), 5);
+        assert!(uuid::Uuid::parse_str(words[2]).is_ok());
+        assert_eq!(lines[2], "With Ana, about  and Ana.");
+        assert_eq!(lines[3], "`{{ not a name }}` {{date:%Q}} {{");
+    }
+}
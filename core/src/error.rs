@@ -0,0 +1,134 @@
+//! Errors reported by `notes_core`.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
+
+#[derive(Debug)]
+pub enum Error {
+    /// No note file exists at the given path
+    NoteNotFound(PathBuf),
+    /// A file is in the way of a note being created or renamed
+    NoteExists(PathBuf),
+    /// No template of this name exists in the templates folder
+    TemplateNotFound(String),
+    /// No task has this id
+    TaskNotFound(String),
+    /// A note has no heading or block by this name
+    AnchorNotFound { note: String, anchor: String },
+    /// This many links point to a heading or block that does not exist
+    DanglingAnchors(usize),
+    /// A title that cannot be used for a note
+    InvalidTitle { title: String, reason: String },
+    /// A note file is not valid UTF-8
+    NotUtf8(PathBuf),
+    /// The operating system refused access to a path
+    PermissionDenied(PathBuf),
+    /// The vault directory does not exist
+    VaultNotFound(PathBuf),
+    /// Any other I/O failure
+    Io { path: PathBuf, source: io::Error },
+    /// A configuration file could not be parsed
+    Config { path: PathBuf, message: String },
+    /// The editor could not be started or failed
+    Editor { command: String, reason: String },
+    /// The vault could not be watched for changes
+    Watch {
+        path: PathBuf,
+        source: notify::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Wrap an I/O error that happened while accessing `path`, picking a more
+    /// specific variant where the error kind allows it. Invalid data is only
+    /// reported as [`Error::NotUtf8`] when it wraps a UTF-8 decoding error.
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        let utf8 = source
+            .get_ref()
+            .is_some_and(|e| e.is::<FromUtf8Error>() || e.is::<Utf8Error>());
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied(path.to_path_buf()),
+            io::ErrorKind::InvalidData if utf8 => Error::NotUtf8(path.to_path_buf()),
+            _ => Error::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+        }
+    }
+
+    pub(crate) fn watch(path: &Path, source: notify::Error) -> Self {
+        Error::Watch {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub(crate) fn config(path: &Path, message: &str) -> Self {
+        Error::Config {
+            path: path.to_path_buf(),
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn editor(command: &str, reason: &str) -> Self {
+        Error::Editor {
+            command: command.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub(crate) fn invalid_title(title: &str, reason: &str) -> Self {
+        Error::InvalidTitle {
+            title: title.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoteNotFound(path) => write!(f, "note not found: {}", path.display()),
+            Error::NoteExists(path) => write!(f, "{} already exists", path.display()),
+            Error::TemplateNotFound(name) => write!(f, "template '{}' not found", name),
+            Error::TaskNotFound(id) => write!(f, "task '{}' not found", id),
+            Error::AnchorNotFound { note, anchor } => {
+                write!(f, "no heading or block '{}' in '{}'", anchor, note)
+            }
+            Error::DanglingAnchors(count) => {
+                write!(f, "{} links to missing headings or blocks", count)
+            }
+            Error::InvalidTitle { title, reason } => {
+                write!(f, "invalid title '{}': {}", title, reason)
+            }
+            Error::NotUtf8(path) => write!(f, "{} is not valid UTF-8 text", path.display()),
+            Error::PermissionDenied(path) => {
+                write!(f, "permission denied: {}", path.display())
+            }
+            Error::VaultNotFound(path) => write!(f, "vault not found: {}", path.display()),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Config { path, message } => {
+                write!(f, "invalid config {}: {}", path.display(), message)
+            }
+            Error::Editor { command, reason } => write!(f, "editor '{}': {}", command, reason),
+            Error::Watch { path, source } => {
+                write!(f, "cannot watch {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Watch { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Minimal YAML frontmatter support.
+//!
+//! Only the subset of YAML that note metadata needs is understood: top level
+//! `key: value` pairs, flow lists (`[a, b]`) and block lists (`- a`). Every
+//! entry keeps the exact text it was read from, so a load/save cycle writes
+//! back comments, key order and unknown fields untouched. Only entries whose
+//! value was actually changed are re-serialized.
+
+/// A parsed value of a frontmatter entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    List(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// `None` for comments and blank lines between entries.
+    key: Option<String>,
+    /// Original text of the entry including its trailing newline.
+    raw: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frontmatter {
+    open: String,
+    close: String,
+    entries: Vec<Entry>,
+}
+
+impl Default for Frontmatter {
+    fn default() -> Self {
+        Frontmatter {
+            open: "---\n".into(),
+            close: "---\n".into(),
+            entries: Vec::new(),
+        }
+    }
+}
+
+fn is_delimiter(line: &str, close: bool) -> bool {
+    let line = line.trim_end_matches(['\r', '\n']);
+    line == "---" || (close && line == "...")
+}
+
+fn entry_key(line: &str) -> Option<String> {
+    if line.starts_with([' ', '\t', '#', '-']) {
+        return None;
+    }
+    let (key, _) = line.split_once(':')?;
+    let key = unquote(key.trim());
+    if key.is_empty() { None } else { Some(key) }
+}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    if s.len() >= 2 {
+        if let Some(inner) = s.strip_prefix('"').and_then(|r| r.strip_suffix('"')) {
+            return inner.replace("\\\"", "\"").replace("\\\\", "\\");
+        }
+        if let Some(inner) = s.strip_prefix('\'').and_then(|r| r.strip_suffix('\'')) {
+            return inner.replace("''", "'");
+        }
+    }
+    s.to_string()
+}
+
+/// Items of a flow list's contents, split on the commas outside quotes.
+fn split_flow(inner: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == ',' => {
+                items.push(&inner[start..i]);
+                start = i + 1;
+            }
+            None => {}
+        }
+    }
+    items.push(&inner[start..]);
+    items
+}
+
+fn strip_comment(s: &str) -> &str {
+    if s.starts_with(['"', '\'']) {
+        return s;
+    }
+    match s.find(" #") {
+        Some(pos) => s[..pos].trim_end(),
+        None => s,
+    }
+}
+
+fn needs_quotes(s: &str) -> bool {
+    s.is_empty()
+        || s != s.trim()
+        || s.starts_with([
+            '-', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%', '@', '`', '?',
+        ])
+        || s.contains(": ")
+        || s.contains(" #")
+        || s.contains(',')
+        || s.ends_with(':')
+}
+
+fn render_scalar(s: &str) -> String {
+    if needs_quotes(s) {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn render_entry(key: &str, value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{}: {}\n", key, render_scalar(s)),
+        Value::List(items) if items.is_empty() => format!("{}: []\n", key),
+        Value::List(items) => {
+            let mut out = format!("{}:\n", key);
+            for item in items {
+                out.push_str(&format!("  - {}\n", render_scalar(item)));
+            }
+            out
+        }
+    }
+}
+
+fn parse_value(raw: &str) -> Value {
+    let mut lines = raw.lines();
+    let first = lines.next().unwrap_or_default();
+    let rest = first
+        .split_once(':')
+        .map(|(_, r)| strip_comment(r.trim()))
+        .unwrap_or_default();
+
+    if let Some(inner) = rest.strip_prefix('[').and_then(|r| r.strip_suffix(']')) {
+        let items = split_flow(inner)
+            .into_iter()
+            .map(unquote)
+            .filter(|s| !s.is_empty())
+            .collect();
+        return Value::List(items);
+    }
+
+    if rest.is_empty() {
+        let items: Vec<String> = lines
+            .filter_map(|l| l.trim().strip_prefix('-'))
+            .map(|item| unquote(strip_comment(item.trim())))
+            .collect();
+        return if items.is_empty() {
+            Value::String(String::new())
+        } else {
+            Value::List(items)
+        };
+    }
+
+    if rest == "|" || rest == ">" {
+        let text: Vec<&str> = lines.map(str::trim).collect();
+        let sep = if rest == "|" { "\n" } else { " " };
+        return Value::String(text.join(sep));
+    }
+
+    Value::String(unquote(rest))
+}
+
+impl Frontmatter {
+    /// Split `text` into its frontmatter block and the remaining body.
+    ///
+    /// A block must start on the very first line with `---` and end with a
+    /// line containing `---` or `...`. Without a closing line the whole text is
+    /// treated as body.
+    pub fn split(text: &str) -> (Option<Frontmatter>, &str) {
+        let Some(first_end) = text.find('\n') else {
+            return (None, text);
+        };
+        let open = &text[..=first_end];
+        if !is_delimiter(open, false) {
+            return (None, text);
+        }
+        let mut pos = first_end + 1;
+        while pos < text.len() {
+            let end = text[pos..].find('\n').map_or(text.len(), |i| pos + i + 1);
+            let line = &text[pos..end];
+            if is_delimiter(line, true) {
+                let mut fm = Frontmatter::parse(&text[first_end + 1..pos]);
+                fm.open = open.to_string();
+                fm.close = line.to_string();
+                if !fm.close.ends_with('\n') {
+                    fm.close.push('\n');
+                }
+                return (Some(fm), &text[end..]);
+            }
+            pos = end;
+        }
+        (None, text)
+    }
+
+    /// Parse the YAML between the delimiters.
+    pub fn parse(yaml: &str) -> Frontmatter {
+        let mut entries: Vec<Entry> = Vec::new();
+        for line in yaml.split_inclusive('\n') {
+            if let Some(key) = entry_key(line) {
+                entries.push(Entry {
+                    key: Some(key),
+                    raw: line.to_string(),
+                });
+                continue;
+            }
+            let standalone = line.trim().is_empty() || line.starts_with('#');
+            match entries.last_mut() {
+                Some(entry) if entry.key.is_some() && !standalone => entry.raw.push_str(line),
+                _ => entries.push(Entry {
+                    key: None,
+                    raw: line.to_string(),
+                }),
+            }
+        }
+        if let Some(last) = entries.last_mut()
+            && !last.raw.ends_with('\n')
+        {
+            last.raw.push('\n');
+        }
+        Frontmatter {
+            entries,
+            ..Frontmatter::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(|e| e.key.is_none())
+    }
+
+    /// Keys in the order they appear in the file.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().filter_map(|e| e.key.as_deref())
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.entries
+            .iter()
+            .find(|e| e.key.as_deref() == Some(key))
+            .map(|e| parse_value(&e.raw))
+    }
+
+    /// Returns the value of `key` if it is a plain string.
+    pub fn get_str(&self, key: &str) -> Option<String> {
+        match self.get(key)? {
+            Value::String(s) if !s.is_empty() => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of `key` as a list. A plain string is treated as a
+    /// list with a single item.
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        match self.get(key) {
+            Some(Value::List(items)) => items,
+            Some(Value::String(s)) if !s.is_empty() => vec![s],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Set `key` to `value`. The original text is kept if the value did not
+    /// change; new keys are appended at the end.
+    pub fn set(&mut self, key: &str, value: Value) {
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|e| e.key.as_deref() == Some(key))
+        {
+            if parse_value(&entry.raw) != value {
+                entry.raw = render_entry(key, &value);
+            }
+            return;
+        }
+        self.entries.push(Entry {
+            key: Some(key.to_string()),
+            raw: render_entry(key, &value),
+        });
+    }
+
+    /// Like [`Frontmatter::set`] for lists, but an empty list does not add a
+    /// key that was never there.
+    pub fn set_list(&mut self, key: &str, items: &[String]) {
+        if items.is_empty() && self.get(key).is_none() {
+            return;
+        }
+        if self.get_list(key) == items {
+            return;
+        }
+        self.set(key, Value::List(items.to_vec()));
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.entries.retain(|e| e.key.as_deref() != Some(key));
+    }
+
+    /// Render the block including its delimiters.
+    pub fn render(&self) -> String {
+        let mut out = self.open.clone();
+        for entry in &self.entries {
+            out.push_str(&entry.raw);
+        }
+        out.push_str(&self.close);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frontmatter, Value};
+
+    #[test]
+    fn round_trip_unchanged() {
+        let text = "---\ntitle: Nuclear Power # primary\n# keep me\naliases:\n- fission power\n-   \"atomic energy\"\nrating: 5\ntags: [energy, physics]\n...\nBody text\n";
+        let (fm, body) = Frontmatter::split(text);
+        let mut fm = fm.unwrap();
+        assert_eq!(body, "Body text\n");
+        assert_eq!(
+            fm.get_list("aliases"),
+            vec!["fission power".to_string(), "atomic energy".to_string()]
+        );
+        assert_eq!(fm.get_list("tags"), vec!["energy", "physics"]);
+        assert_eq!(fm.get_str("title").as_deref(), Some("Nuclear Power"));
+        assert_eq!(
+            fm.keys().collect::<Vec<_>>(),
+            ["title", "aliases", "rating", "tags"]
+        );
+
+        fm.set_list("aliases", &["fission power".into(), "atomic energy".into()]);
+        assert_eq!(fm.render() + body, text);
+    }
+
+    #[test]
+    fn quoted_commas_in_flow_lists() {
+        let text =
+            "---\naliases: [\"Smith, John\", JS, 'O''Brien, P', \"say \\\"hi, there\\\"\"]\n---\n";
+        let (fm, _) = Frontmatter::split(text);
+        let mut fm = fm.unwrap();
+        let aliases = fm.get_list("aliases");
+        assert_eq!(
+            aliases,
+            ["Smith, John", "JS", "O'Brien, P", "say \"hi, there\""]
+        );
+        assert_eq!(fm.render(), text);
+
+        let mut aliases = aliases;
+        aliases.push("Smith, J.".into());
+        fm.set_list("aliases", &aliases);
+        let text = fm.render();
+        assert!(text.contains("  - \"Smith, J.\"\n"), "{}", text);
+        let (fm, _) = Frontmatter::split(&text);
+        assert_eq!(fm.unwrap().get_list("aliases"), aliases);
+    }
+
+    #[test]
+    fn changed_values_are_rewritten_in_place() {
+        let text = "---\naliases: one\nrating: 5\n---\n";
+        let (fm, _) = Frontmatter::split(text);
+        let mut fm = fm.unwrap();
+        fm.set_list("aliases", &["one".into(), "two: three".into()]);
+        fm.set_list("tags", &[]);
+        fm.set("created", Value::String("2024-01-01".into()));
+        assert_eq!(
+            fm.render(),
+            "---\naliases:\n  - one\n  - \"two: three\"\nrating: 5\ncreated: 2024-01-01\n---\n"
+        );
+    }
+
+    #[test]
+    fn no_frontmatter() {
+        let (fm, body) = Frontmatter::split("---\nnot closed");
+        assert!(fm.is_none());
+        assert_eq!(body, "---\nnot closed");
+        let (fm, body) = Frontmatter::split("plain text");
+        assert!(fm.is_none());
+        assert_eq!(body, "plain text");
+    }
+}
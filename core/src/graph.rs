@@ -1,48 +1,190 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::hash::Hasher;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
 
-use crate::note::vault_dir;
+use aho_corasick::{AhoCorasick, MatchKind};
+use serde::{Deserialize, Serialize};
+use twox_hash::XxHash64;
 
-#[derive(Debug)]
+use crate::cache;
+use crate::config::config;
+use crate::error::{Error, Result};
+use crate::frontmatter::Frontmatter;
+use crate::links::parse_links;
+use crate::note::{normalize_title, vault_dir};
+use crate::periodic;
+use crate::tags::inline_tags;
+use crate::vault::{STATE_DIR, is_ignored, walk};
+
+#[derive(Debug, Default)]
 pub struct Graph {
     pub nodes: Vec<Node>,
-    /// Directed edges using node indices (from -> to)
-    pub edges: Vec<(usize, usize)>,
+    /// Directed edges, sorted by node indices
+    pub edges: Vec<Edge>,
+    /// Indices into `edges` of the links to each node
+    incoming: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    /// Links from node `i`, sorted by target.
+    pub fn outgoing(&self, i: usize) -> &[Edge] {
+        let start = self.edges.partition_point(|e| e.from < i);
+        let end = self.edges.partition_point(|e| e.from <= i);
+        &self.edges[start..end]
+    }
+
+    /// Links to node `i`, sorted by source.
+    pub fn incoming(&self, i: usize) -> impl Iterator<Item = &Edge> {
+        self.incoming
+            .get(i)
+            .into_iter()
+            .flatten()
+            .map(|&k| &self.edges[k])
+    }
+
+    fn index_edges(&mut self) {
+        self.incoming = vec![Vec::new(); self.nodes.len()];
+        for (k, edge) in self.edges.iter().enumerate() {
+            self.incoming[edge.to].push(k);
+        }
+    }
+}
+
+/// A directed link between two nodes, by index into [`Graph::nodes`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: LinkKind,
+    /// The alias of the target that produced the link, or `None` if its name
+    /// matched directly
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LinkKind {
+    /// The target's name or alias appears in the text
+    Implicit,
+    /// A `[[wikilink]]` or Markdown link to the target
+    Explicit,
+}
+
+/// Which kinds of links a graph is built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMode {
+    Implicit,
+    Explicit,
+    #[default]
+    Both,
 }
 
-#[derive(Debug, Clone)]
+impl LinkMode {
+    fn includes(self, kind: LinkKind) -> bool {
+        match self {
+            LinkMode::Implicit => kind == LinkKind::Implicit,
+            LinkMode::Explicit => kind == LinkKind::Explicit,
+            LinkMode::Both => true,
+        }
+    }
+}
+
+impl FromStr for LinkMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "implicit" => Ok(LinkMode::Implicit),
+            "explicit" => Ok(LinkMode::Explicit),
+            "both" => Ok(LinkMode::Both),
+            _ => Err(format!(
+                "unknown link mode '{}', expected implicit, explicit or both",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     /// Base name of the note without extension
     pub name: String,
+    /// Path relative to the vault without extension, e.g. `projects/Plan`.
+    /// Files with the same canonical name in the same folder form one node.
+    pub rel_path: PathBuf,
     /// All files that belong to this logical node
     pub paths: Vec<PathBuf>,
+    /// Alternative names declared in the frontmatter of the node's files.
+    /// Each alias links to this node exactly like its name does.
+    pub aliases: Vec<String>,
+    /// Tags declared in the frontmatter of the node's files followed by the
+    /// inline `#tags` of their bodies, without `#`
+    pub tags: Vec<String>,
     /// Number of links connected to this node (in or out)
     pub links: usize,
 }
 
 impl Node {
-    /// Returns true if this logical node only represents directories.
-    pub fn is_directory(&self) -> bool {
-        self.paths.iter().all(|p| p.is_dir())
+    /// Folder of the node relative to the vault root.
+    pub fn folder(&self) -> &Path {
+        self.rel_path.parent().unwrap_or(Path::new(""))
+    }
+
+    /// Lowercase extensions of the node's files, sorted and without
+    /// duplicates.
+    pub fn formats(&self) -> Vec<String> {
+        let mut formats: Vec<String> = self
+            .paths
+            .iter()
+            .filter_map(|p| p.extension().and_then(|e| e.to_str()))
+            .map(|e| e.to_ascii_lowercase())
+            .collect();
+        formats.sort();
+        formats.dedup();
+        formats
+    }
+
+    /// Latest modification time of the node's files.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.paths.iter().filter_map(|p| modified_time(p)).max()
+    }
+
+    /// The file to open when editing this note: the one in the configured
+    /// default format, else the first text file by extension.
+    pub fn text_file(&self) -> Option<&PathBuf> {
+        let default_format = &config().default_format;
+        self.paths
+            .iter()
+            .find(|p| {
+                p.extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case(default_format))
+            })
+            .or_else(|| {
+                self.paths
+                    .iter()
+                    .filter(|p| is_text_file(p))
+                    .min_by_key(|p| p.extension().map(|e| e.to_ascii_lowercase()))
+            })
     }
 
     /// Determine the primary file format of this node.
     ///
     /// Binary formats have highest priority, followed by text formats in
-    /// alphabetical order. Markdown is only used if no other text format exists.
+    /// alphabetical order. Unless the `markdown_last` setting is turned off,
+    /// Markdown is only used if no other text format exists.
     pub fn primary_file_format(&self) -> Option<String> {
         let mut binaries = Vec::new();
         let mut texts = Vec::new();
         let mut has_md = false;
         for path in &self.paths {
-            if path.is_dir() {
-                continue;
-            }
             if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
                 let ext_lc = ext.to_ascii_lowercase();
                 if is_text_file(path) {
-                    if ext_lc == "md" {
+                    if ext_lc == "md" && config().markdown_last {
                         has_md = true;
                     } else {
                         texts.push(ext_lc);
@@ -66,7 +208,7 @@ impl Node {
     }
 }
 
-fn normalize(s: &str) -> String {
+pub(crate) fn normalize(s: &str) -> String {
     let mut out = String::new();
     let mut in_space = false;
     for c in s.chars() {
@@ -83,7 +225,7 @@ fn normalize(s: &str) -> String {
     out
 }
 
-fn canonicalize(s: &str) -> String {
+pub(crate) fn canonicalize(s: &str) -> String {
     normalize(s).replace(' ', "")
 }
 
@@ -99,279 +241,1306 @@ fn is_end_boundary(text: &str, idx: usize) -> bool {
     idx == text.len() || text.as_bytes()[idx].is_ascii_whitespace()
 }
 
-struct Match {
-    start: usize,
-    end: usize,
-    idx: usize,
+/// Finds all occurrences of a fixed list of normalized names in normalized
+/// text in a single pass.
+///
+/// Matches must start and end on word boundaries. Overlapping matches are all
+/// kept, except that a match lying inside a longer one is dropped, so
+/// "nuclear power" wins over "power" but "a b" and "b c" both match "a b c".
+#[derive(Debug, Default)]
+struct Matcher {
+    automaton: Option<AhoCorasick>,
+    /// Indices into the name list of each distinct pattern
+    names: Vec<Vec<usize>>,
 }
 
-fn find_unique_links(text: &str, _canon: &[String], names: &[String]) -> Vec<usize> {
-    let mut matches = Vec::new();
-    for (i, name) in names.iter().enumerate() {
-        let mut search_start = 0;
-        while let Some(pos) = text[search_start..].find(name) {
-            let start = search_start + pos;
-            let end = start + name.len();
-            if is_boundary(text, start) && is_end_boundary(text, end) {
-                matches.push(Match { start, end, idx: i });
+impl Matcher {
+    fn new(names: &[String]) -> Self {
+        let mut patterns: Vec<&str> = Vec::new();
+        let mut by_pattern: HashMap<&str, usize> = HashMap::new();
+        let mut indices: Vec<Vec<usize>> = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            if name.is_empty() {
+                continue;
             }
-            search_start = start + 1;
+            let id = *by_pattern.entry(name).or_insert_with(|| {
+                patterns.push(name);
+                indices.push(Vec::new());
+                patterns.len() - 1
+            });
+            indices[id].push(i);
+        }
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::Standard)
+            .build(&patterns)
+            .ok();
+        Matcher {
+            automaton,
+            names: indices,
         }
     }
 
-    let mut keep = vec![true; matches.len()];
-    for i in 0..matches.len() {
-        for j in 0..matches.len() {
-            if i == j {
-                continue;
+    /// Indices of the names found in `text`, sorted and deduplicated.
+    fn find(&self, text: &str) -> Vec<usize> {
+        self.find_with_shadowed(text).0
+    }
+
+    /// Indices of the names found in `text`, and of the names that were only
+    /// found inside a longer match, each sorted and deduplicated.
+    fn find_with_shadowed(&self, text: &str) -> (Vec<usize>, Vec<usize>) {
+        let Some(automaton) = &self.automaton else {
+            return (Vec::new(), Vec::new());
+        };
+        let mut matches: Vec<(usize, usize, usize)> = automaton
+            .find_overlapping_iter(text)
+            .filter(|m| is_boundary(text, m.start()) && is_end_boundary(text, m.end()))
+            .map(|m| (m.start(), m.end(), m.pattern().as_usize()))
+            .collect();
+        // longer matches come first among those starting at the same place,
+        // so any match containing another one is seen before it. Patterns are
+        // distinct, so no two matches share a span.
+        matches.sort_unstable_by_key(|&(start, end, _)| (start, Reverse(end)));
+
+        let mut result = Vec::new();
+        let mut shadowed = Vec::new();
+        let mut max_end = 0;
+        for (_, end, id) in matches {
+            if end > max_end {
+                result.extend_from_slice(&self.names[id]);
+                max_end = end;
+            } else {
+                shadowed.extend_from_slice(&self.names[id]);
             }
-            let a = &matches[i];
-            let b = &matches[j];
-            if b.start <= a.start && b.end >= a.end && (b.end - b.start) > (a.end - a.start) {
-                keep[i] = false;
-                break;
+        }
+        result.sort_unstable();
+        result.dedup();
+        shadowed.sort_unstable();
+        shadowed.dedup();
+        shadowed.retain(|k| result.binary_search(k).is_err());
+        (result, shadowed)
+    }
+}
+
+pub fn build_graph() -> Result<Graph> {
+    Ok(load_graph_data()?.graph)
+}
+
+/// Modification time and content hash of a file when it was last read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileState {
+    modified: Option<SystemTime>,
+    hash: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct GraphData {
+    pub graph: Graph,
+    /// Vault the data was loaded from
+    root: PathBuf,
+    /// Canonical stem of every node, by node index
+    canonical: Vec<String>,
+    /// Nodes sharing each canonical stem, in order of preference
+    by_canonical: HashMap<String, Vec<usize>>,
+    /// Normalized stems followed by normalized aliases
+    normalized: Vec<String>,
+    /// Node and alias (if any) for each entry in `normalized`
+    targets: Vec<(usize, Option<String>)>,
+    /// Matcher over `normalized`, rebuilt whenever the names change
+    matcher: Matcher,
+    contents: Vec<String>,
+    /// Targets of the explicit links in each node, as written
+    explicit: Vec<Vec<String>>,
+    /// Outgoing edges of each node, sorted by target
+    outgoing: Vec<Vec<Edge>>,
+    /// State of every file of the vault when it was last read
+    files: HashMap<PathBuf, FileState>,
+    /// Node of every file of the vault
+    by_path: HashMap<PathBuf, usize>,
+    /// Words and link targets of every node, to find the nodes a changed
+    /// name concerns
+    mentions: MentionIndex,
+    mode: LinkMode,
+    /// Folders of the vault that could not be read when it was last walked;
+    /// the notes in them are missing from the graph, or kept as they were
+    pub skipped: Vec<Error>,
+}
+
+/// Nodes touched by an update, by their path relative to the vault (see
+/// [`Node::rel_path`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+}
+
+impl GraphData {
+    /// Find the note a title typed by the user refers to. Titles are matched
+    /// like links written at the vault root: by canonical name, then by alias,
+    /// and `folder/Name` picks a note in a folder.
+    pub fn find(&self, title: &str) -> Option<usize> {
+        resolve_target(self, &normalize_title(title), Path::new("")).map(|(i, _)| i)
+    }
+
+    /// Vault the data was loaded from.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The nodes using each word of their text and linking to each name, so
+/// that when a name appears or disappears only the nodes that can mention it
+/// are scanned again.
+#[derive(Debug, Default)]
+struct MentionIndex {
+    /// Nodes whose normalized text contains each word
+    words: HashMap<String, HashSet<usize>>,
+    /// Nodes with an explicit link to each canonical stem
+    links: HashMap<String, HashSet<usize>>,
+}
+
+/// Canonical stem of an explicit link target, as written.
+fn target_stem(target: &str) -> String {
+    let stem = Path::new(target)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(target);
+    canonicalize(stem)
+}
+
+impl MentionIndex {
+    /// Record the normalized text and explicit link targets of node `i`.
+    fn add(&mut self, i: usize, contents: &str, explicit: &[String]) {
+        for word in contents.split(' ').filter(|w| !w.is_empty()) {
+            if let Some(nodes) = self.words.get_mut(word) {
+                nodes.insert(i);
+            } else {
+                self.words.insert(word.to_string(), HashSet::from([i]));
             }
         }
+        for target in explicit {
+            self.links.entry(target_stem(target)).or_default().insert(i);
+        }
     }
 
-    let mut result = Vec::new();
-    for (m, &k) in matches.iter().zip(&keep) {
-        if k {
-            result.push(m.idx);
+    /// Forget what [`MentionIndex::add`] recorded for node `i`.
+    fn remove(&mut self, i: usize, contents: &str, explicit: &[String]) {
+        for word in contents.split(' ') {
+            if let Some(nodes) = self.words.get_mut(word) {
+                nodes.remove(&i);
+                if nodes.is_empty() {
+                    self.words.remove(word);
+                }
+            }
+        }
+        for target in explicit {
+            let stem = target_stem(target);
+            if let Some(nodes) = self.links.get_mut(&stem) {
+                nodes.remove(&i);
+                if nodes.is_empty() {
+                    self.links.remove(&stem);
+                }
+            }
         }
     }
-    result.sort_unstable();
-    result.dedup();
-    result
-}
 
-pub fn build_graph() -> Graph {
-    let mut nodes = Vec::new();
-    let mut canonical = Vec::new();
-    let mut normalized = Vec::new();
-    let mut index_map: HashMap<String, usize> = HashMap::new();
+    /// Move every node to its new index after nodes were removed.
+    fn remap(&mut self, new_index: &[Option<usize>]) {
+        for nodes in self.words.values_mut().chain(self.links.values_mut()) {
+            *nodes = nodes.iter().filter_map(|&i| new_index[i]).collect();
+        }
+    }
 
-    if let Ok(entries) = fs::read_dir(vault_dir()) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                let canon = canonicalize(stem);
-                let idx = if let Some(idx) = index_map.get(&canon).copied() {
-                    idx
-                } else {
-                    let idx = nodes.len();
-                    nodes.push(Node {
-                        name: stem.to_string(),
-                        paths: Vec::new(),
-                        links: 0,
-                    });
-                    index_map.insert(canon.clone(), idx);
-                    canonical.push(canon);
-                    normalized.push(normalize(stem));
-                    idx
-                };
-                nodes[idx].paths.push(path.clone());
+    /// Nodes whose text may mention the normalized `name`: those using
+    /// every word of it.
+    fn candidates(&self, name: &str) -> HashSet<usize> {
+        let mut sets: Vec<&HashSet<usize>> = Vec::new();
+        for word in name.split(' ') {
+            match self.words.get(word) {
+                Some(nodes) => sets.push(nodes),
+                None => return HashSet::new(),
             }
         }
+        sets.sort_by_key(|nodes| nodes.len());
+        let Some((first, rest)) = sets.split_first() else {
+            return HashSet::new();
+        };
+        first
+            .iter()
+            .copied()
+            .filter(|i| rest.iter().all(|nodes| nodes.contains(i)))
+            .collect()
     }
+}
 
-    // store directed edges using indices
-    let mut edges: HashSet<(usize, usize)> = HashSet::new();
+fn read_file(path: &Path) -> Result<(FileState, Vec<u8>)> {
+    let modified = modified_time(path);
+    let bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(&bytes);
+    let state = FileState {
+        modified,
+        hash: hasher.finish(),
+    };
+    Ok((state, bytes))
+}
 
-    for i in 0..nodes.len() {
-        let mut content = String::new();
-        for path in &nodes[i].paths {
-            if is_text_file(path) {
-                if let Ok(text) = fs::read_to_string(path) {
-                    content.push_str(&text);
-                    content.push('\n');
+struct NodeText {
+    /// Bodies of all text files, without frontmatter
+    text: String,
+    /// Aliases declared in the frontmatter
+    aliases: Vec<String>,
+    /// Tags declared in the frontmatter or inline
+    tags: Vec<String>,
+    /// Targets of explicit links in the bodies
+    explicit: Vec<String>,
+    /// State of every file read
+    files: Vec<(PathBuf, FileState)>,
+}
+
+/// Read the files of a node. Files that are not UTF-8 are binary assets and
+/// only contribute their file state.
+fn read_node(node: &Node) -> Result<NodeText> {
+    let mut out = NodeText {
+        text: String::new(),
+        aliases: Vec::new(),
+        tags: Vec::new(),
+        explicit: Vec::new(),
+        files: Vec::new(),
+    };
+    for path in &node.paths {
+        let (state, bytes) = read_file(path)?;
+        out.files.push((path.clone(), state));
+        let Ok(content) = String::from_utf8(bytes) else {
+            continue;
+        };
+        let (frontmatter, body) = Frontmatter::split(&content);
+        if let Some(fm) = frontmatter {
+            for alias in fm.get_list("aliases") {
+                if !out.aliases.contains(&alias) {
+                    out.aliases.push(alias);
+                }
+            }
+            for tag in fm.get_list("tags") {
+                let tag = tag.trim_start_matches('#').to_string();
+                if !tag.is_empty() && !out.tags.contains(&tag) {
+                    out.tags.push(tag);
                 }
             }
         }
-        let text = normalize(&content);
-        for j in find_unique_links(&text, &canonical, &normalized) {
-            if i == j {
-                continue;
+        for tag in inline_tags(body) {
+            if !out.tags.contains(&tag) {
+                out.tags.push(tag);
             }
-            edges.insert((i, j));
         }
+        for link in parse_links(body) {
+            if !link.target.is_empty() && !out.explicit.contains(&link.target) {
+                out.explicit.push(link.target);
+            }
+        }
+        out.text.push_str(body);
+        out.text.push('\n');
+    }
+    Ok(out)
+}
+
+/// Read node `i` again. Returns its previous aliases if they changed.
+fn reload_node(data: &mut GraphData, i: usize) -> Result<Option<Vec<String>>> {
+    let read = read_node(&data.graph.nodes[i])?;
+    data.files.extend(read.files);
+    data.mentions
+        .remove(i, &data.contents[i], &data.explicit[i]);
+    data.contents[i] = normalize(&read.text);
+    data.explicit[i] = read.explicit;
+    data.mentions.add(i, &data.contents[i], &data.explicit[i]);
+    let node = &mut data.graph.nodes[i];
+    node.tags = read.tags;
+    if node.aliases == read.aliases {
+        return Ok(None);
     }
+    Ok(Some(std::mem::replace(&mut node.aliases, read.aliases)))
+}
 
-    // count links for each node
-    let mut link_counts = vec![0usize; nodes.len()];
-    for &(a, b) in &edges {
-        if a < link_counts.len() {
-            link_counts[a] += 1;
+/// Canonical form of each folder in `path`, with `.` and `..` resolved.
+fn canonical_folders(path: &Path) -> Vec<String> {
+    let mut out = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::Normal(name) => out.push(canonicalize(&name.to_string_lossy())),
+            _ => {}
         }
-        if b < link_counts.len() {
-            link_counts[b] += 1;
+    }
+    out
+}
+
+/// Pick the node a bare name refers to when several notes share it.
+///
+/// Notes with the same name in different folders are separate nodes. A name
+/// written in a note resolves to the note with that name in the same folder
+/// if there is one, otherwise to the one closest to the vault root, ties
+/// broken alphabetically by path.
+fn pick_candidate(data: &GraphData, candidates: &[usize], folder: &Path) -> usize {
+    candidates
+        .iter()
+        .copied()
+        .find(|&j| data.graph.nodes[j].folder() == folder)
+        .unwrap_or(candidates[0])
+}
+
+/// Find the node an explicit link from a note in `from_folder` points at.
+///
+/// Names are compared by their canonical form and a node's own name wins over
+/// another node's alias. A target with folders (`projects/Plan`) is looked up
+/// relative to the linking note first, then relative to the vault root, and
+/// finally matched against the end of each candidate's folder path.
+pub(crate) fn resolve_target(
+    data: &GraphData,
+    target: &str,
+    from_folder: &Path,
+) -> Option<(usize, Option<String>)> {
+    let target_path = Path::new(target);
+    let stem = target_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(target);
+    let canon = canonicalize(stem);
+    if let Some(candidates) = data.by_canonical.get(&canon) {
+        let folder = target_path.parent().unwrap_or(Path::new(""));
+        if folder.as_os_str().is_empty() {
+            return Some((pick_candidate(data, candidates, from_folder), None));
         }
+        let wanted = canonical_folders(folder);
+        let folder_of = |j: usize| canonical_folders(data.graph.nodes[j].folder());
+        let found = [canonical_folders(&from_folder.join(folder)), wanted.clone()]
+            .iter()
+            .find_map(|base| candidates.iter().copied().find(|&j| &folder_of(j) == base))
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .copied()
+                    .find(|&j| folder_of(j).ends_with(&wanted))
+            });
+        return found.map(|j| (j, None));
     }
-    for (node, count) in nodes.iter_mut().zip(link_counts) {
-        node.links = count;
+    data.targets
+        .iter()
+        .find(|(_, alias)| alias.as_deref().is_some_and(|a| canonicalize(a) == canon))
+        .cloned()
+}
+
+/// Rebuild the list of names that links are matched against: every node
+/// stem first, then every alias.
+fn rebuild_names(data: &mut GraphData) {
+    data.normalized.clear();
+    data.targets.clear();
+    data.by_canonical.clear();
+    let mut order: Vec<usize> = (0..data.graph.nodes.len()).collect();
+    order.sort_by_key(|&i| {
+        let path = &data.graph.nodes[i].rel_path;
+        (path.components().count(), path.clone())
+    });
+    for i in order {
+        data.by_canonical
+            .entry(data.canonical[i].clone())
+            .or_default()
+            .push(i);
     }
+    for (i, node) in data.graph.nodes.iter().enumerate() {
+        data.normalized.push(normalize(&node.name));
+        data.targets.push((i, None));
+    }
+    for (i, node) in data.graph.nodes.iter().enumerate() {
+        for alias in &node.aliases {
+            let norm = normalize(alias);
+            if !norm.is_empty() {
+                data.normalized.push(norm);
+                data.targets.push((i, Some(alias.clone())));
+            }
+        }
+        for form in periodic::date_aliases(&node.name, node.folder()) {
+            data.normalized.push(normalize(&form));
+            data.targets.push((i, Some(form)));
+        }
+    }
+    data.matcher = Matcher::new(&data.normalized);
+}
 
-    Graph {
-        nodes,
-        edges: edges.into_iter().collect(),
+/// The node and alias that name `k` in [`GraphData::normalized`] refers to,
+/// when it is mentioned in a note in `folder`.
+fn implicit_target(data: &GraphData, k: usize, folder: &Path) -> (usize, Option<String>) {
+    let (j, alias) = data.targets[k].clone();
+    if alias.is_some() {
+        return (j, alias);
     }
+    let j = pick_candidate(data, &data.by_canonical[&data.canonical[j]], folder);
+    (j, None)
 }
 
-#[derive(Debug)]
-pub struct GraphData {
-    pub graph: Graph,
-    canonical: Vec<String>,
-    normalized: Vec<String>,
-    contents: Vec<String>,
+/// Nodes whose name or alias node `i` mentions without linking to them:
+/// mentions inside a longer name, or any mention when implicit links are
+/// turned off. Sorted by node index.
+pub(crate) fn unlinked_targets(data: &GraphData, i: usize) -> Vec<usize> {
+    let folder = data.graph.nodes[i].folder();
+    let (found, shadowed) = data.matcher.find_with_shadowed(&data.contents[i]);
+    let mut mentioned = shadowed;
+    if !data.mode.includes(LinkKind::Implicit) {
+        mentioned.extend(found);
+    }
+    let linked: HashSet<usize> = data.graph.outgoing(i).iter().map(|e| e.to).collect();
+    let mut targets: Vec<usize> = mentioned
+        .into_iter()
+        .map(|k| implicit_target(data, k, folder).0)
+        .filter(|&j| j != i && !linked.contains(&j))
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+    targets
 }
 
-fn recompute_edges(data: &mut GraphData) {
-    let n = data.graph.nodes.len();
-    let mut edges: HashSet<(usize, usize)> = HashSet::new();
-    for i in 0..n {
+/// Outgoing edges of node `i`, sorted by target. An explicit link wins over
+/// an implicit one to the same note.
+fn node_edges(data: &GraphData, i: usize) -> Vec<Edge> {
+    let mut edges: HashMap<usize, (LinkKind, Option<String>)> = HashMap::new();
+    let folder = data.graph.nodes[i].folder();
+    if data.mode.includes(LinkKind::Explicit) {
+        for target in &data.explicit[i] {
+            if let Some((j, alias)) = resolve_target(data, target, folder)
+                && i != j
+            {
+                edges.entry(j).or_insert((LinkKind::Explicit, alias));
+            }
+        }
+    }
+    if data.mode.includes(LinkKind::Implicit) {
         let text = &data.contents[i];
-        for j in find_unique_links(text, &data.canonical, &data.normalized) {
+        // name indices come back sorted, so a stem match wins over an alias
+        for k in data.matcher.find(text) {
+            let (j, alias) = implicit_target(data, k, folder);
             if i == j {
                 continue;
             }
-            edges.insert((i, j));
+            edges.entry(j).or_insert((LinkKind::Implicit, alias));
         }
     }
+    let mut edges: Vec<Edge> = edges
+        .into_iter()
+        .map(|(to, (kind, alias))| Edge {
+            from: i,
+            to,
+            kind,
+            alias,
+        })
+        .collect();
+    edges.sort_by_key(|e| e.to);
+    edges
+}
+
+/// Recompute the outgoing edges of the given nodes, then rebuild the edge
+/// list and link counts of the graph.
+fn recompute_edges_of(data: &mut GraphData, nodes: impl IntoIterator<Item = usize>) {
+    let n = data.graph.nodes.len();
+    data.outgoing.resize(n, Vec::new());
+    for i in nodes {
+        data.outgoing[i] = node_edges(data, i);
+    }
 
     let mut link_counts = vec![0usize; n];
-    for &(a, b) in &edges {
-        if a < n {
-            link_counts[a] += 1;
-        }
-        if b < n {
-            link_counts[b] += 1;
-        }
+    for edge in data.outgoing.iter().flatten() {
+        link_counts[edge.from] += 1;
+        link_counts[edge.to] += 1;
     }
     for (node, count) in data.graph.nodes.iter_mut().zip(link_counts) {
         node.links = count;
     }
-    data.graph.edges = edges.into_iter().collect();
+    data.graph.edges = data.outgoing.iter().flatten().cloned().collect();
+    data.graph.index_edges();
 }
 
-pub fn load_graph_data() -> GraphData {
-    let mut nodes = Vec::new();
-    let mut canonical = Vec::new();
-    let mut normalized = Vec::new();
-    let mut contents = Vec::new();
+fn recompute_edges(data: &mut GraphData) {
+    let n = data.graph.nodes.len();
+    recompute_edges_of(data, 0..n);
+}
 
-    let mut index_map: HashMap<String, usize> = HashMap::new();
+/// Add an empty node and its bookkeeping, returning its index.
+fn push_node(data: &mut GraphData, folder: &Path, stem: &str) -> usize {
+    data.graph.nodes.push(Node {
+        name: stem.to_string(),
+        rel_path: folder.join(stem),
+        paths: Vec::new(),
+        aliases: Vec::new(),
+        tags: Vec::new(),
+        links: 0,
+    });
+    data.canonical.push(canonicalize(stem));
+    data.contents.push(String::new());
+    data.explicit.push(Vec::new());
+    data.outgoing.push(Vec::new());
+    data.graph.nodes.len() - 1
+}
 
-    if let Ok(entries) = fs::read_dir(vault_dir()) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                let canon = canonicalize(stem);
-                let idx = if let Some(idx) = index_map.get(&canon).copied() {
-                    idx
-                } else {
-                    let idx = nodes.len();
-                    nodes.push(Node {
-                        name: stem.to_string(),
-                        paths: Vec::new(),
-                        links: 0,
-                    });
-                    index_map.insert(canon.clone(), idx);
-                    canonical.push(canon);
-                    normalized.push(normalize(stem));
-                    contents.push(String::new());
-                    idx
-                };
-                nodes[idx].paths.push(path.clone());
-            }
+/// Folder relative to the vault and stem of a file in the vault.
+fn split_rel<'a>(root: &Path, path: &'a Path) -> Option<(PathBuf, &'a str)> {
+    let stem = path.file_stem().and_then(|s| s.to_str())?;
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    let folder = rel.parent().unwrap_or(Path::new("")).to_path_buf();
+    Some((folder, stem))
+}
+
+/// Load the graph of the vault, starting from the cached index in
+/// `.notes/index` when there is a usable one. Only notes that changed since
+/// the cache was written are read.
+pub fn load_graph_data() -> Result<GraphData> {
+    open(&vault_dir())
+}
+
+fn open(root: &Path) -> Result<GraphData> {
+    if let Some(mut data) = cache::read(root).and_then(|s| from_snapshot(root, s)) {
+        if !refresh(&mut data)?.is_empty() {
+            // the cache only saves time, a read-only vault works without it
+            let _ = save_index(&data);
         }
+        return Ok(data);
     }
+    let data = load_from(root)?;
+    let _ = save_index(&data);
+    Ok(data)
+}
 
-    for (i, node) in nodes.iter().enumerate() {
-        let mut text = String::new();
-        for path in &node.paths {
-            if is_text_file(path) {
-                if let Ok(t) = fs::read_to_string(path) {
-                    text.push_str(&t);
-                    text.push('\n');
-                }
-            }
-        }
-        contents[i] = normalize(&text);
+/// Write `data` to the vault's index cache so the next load can skip the
+/// notes that did not change.
+pub fn save_index(data: &GraphData) -> Result<()> {
+    let rel = |path: &PathBuf| path.strip_prefix(&data.root).unwrap_or(path).to_path_buf();
+    let mut snapshot = cache::Snapshot::new(data.mode);
+    snapshot.nodes = data
+        .graph
+        .nodes
+        .iter()
+        .map(|node| Node {
+            paths: node.paths.iter().map(rel).collect(),
+            ..node.clone()
+        })
+        .collect();
+    snapshot.contents = data.contents.clone();
+    snapshot.explicit = data.explicit.clone();
+    snapshot.outgoing = data.outgoing.clone();
+    snapshot.files = data
+        .files
+        .iter()
+        .map(|(path, state)| (rel(path), *state))
+        .collect();
+    cache::write(&data.root, &snapshot)
+}
+
+/// Restore graph data from a cache snapshot. Gives `None` if the snapshot was
+/// made with another link mode.
+fn from_snapshot(root: &Path, snapshot: cache::Snapshot) -> Option<GraphData> {
+    if snapshot.mode != config().link_mode {
+        return None;
+    }
+    let mut data = GraphData {
+        root: root.to_path_buf(),
+        mode: snapshot.mode,
+        ..GraphData::default()
+    };
+    data.canonical = snapshot
+        .nodes
+        .iter()
+        .map(|n| canonicalize(&n.name))
+        .collect();
+    data.graph.nodes = snapshot
+        .nodes
+        .into_iter()
+        .map(|node| Node {
+            paths: node.paths.iter().map(|p| root.join(p)).collect(),
+            ..node
+        })
+        .collect();
+    data.contents = snapshot.contents;
+    data.explicit = snapshot.explicit;
+    data.outgoing = snapshot.outgoing;
+    data.files = snapshot
+        .files
+        .into_iter()
+        .map(|(path, state)| (root.join(path), state))
+        .collect();
+    for (i, node) in data.graph.nodes.iter().enumerate() {
+        data.by_path
+            .extend(node.paths.iter().map(|path| (path.clone(), i)));
+        data.mentions.add(i, &data.contents[i], &data.explicit[i]);
     }
+    rebuild_names(&mut data);
+    recompute_edges_of(&mut data, []);
+    Some(data)
+}
 
+pub(crate) fn load_from(root: &Path) -> Result<GraphData> {
     let mut data = GraphData {
-        graph: Graph {
-            nodes,
-            edges: Vec::new(),
-        },
-        canonical,
-        normalized,
-        contents,
+        root: root.to_path_buf(),
+        mode: config().link_mode,
+        ..GraphData::default()
     };
+
+    // nodes are keyed by folder and canonical stem
+    let mut index_map: HashMap<(PathBuf, String), usize> = HashMap::new();
+
+    let walked = walk(root, &config().ignore)?;
+    data.skipped = walked.skipped.into_iter().map(|(_, e)| e).collect();
+    for path in walked.files {
+        let Some((folder, stem)) = split_rel(root, &path) else {
+            continue;
+        };
+        let key = (folder.clone(), canonicalize(stem));
+        let idx = match index_map.get(&key) {
+            Some(&idx) => idx,
+            None => {
+                let idx = push_node(&mut data, &folder, stem);
+                index_map.insert(key, idx);
+                idx
+            }
+        };
+        data.graph.nodes[idx].paths.push(path.clone());
+        data.by_path.insert(path, idx);
+    }
+
+    for i in 0..data.graph.nodes.len() {
+        reload_node(&mut data, i)?;
+    }
+    rebuild_names(&mut data);
     recompute_edges(&mut data);
-    data
+    Ok(data)
 }
 
-pub fn update_open_notes(data: &mut GraphData, open_notes: &[String]) {
-    for name in open_notes {
-        if let Some(stem) = PathBuf::from(name).file_stem().and_then(|s| s.to_str()) {
-            let canon = canonicalize(stem);
-            if let Some(idx) = data.canonical.iter().position(|c| c == &canon) {
-                let mut text = String::new();
-                for path in &data.graph.nodes[idx].paths {
-                    if is_text_file(path) {
-                        if let Ok(content) = fs::read_to_string(path) {
-                            text.push_str(&content);
-                            text.push('\n');
-                        }
+/// Map a path reported from outside (possibly absolute) onto the form used
+/// for the vault's files. Paths outside the vault or below an ignored folder
+/// give `None`.
+fn vault_path(data: &GraphData, path: &Path) -> Option<PathBuf> {
+    let rel = match path.strip_prefix(&data.root) {
+        Ok(rel) => rel.to_path_buf(),
+        Err(_) => {
+            let root = std::path::absolute(&data.root).ok()?;
+            path.strip_prefix(root).ok()?.to_path_buf()
+        }
+    };
+    let ignore = &config().ignore;
+    if rel.starts_with(STATE_DIR)
+        || rel
+            .components()
+            .any(|c| is_ignored(&c.as_os_str().to_string_lossy(), ignore))
+    {
+        return None;
+    }
+    Some(data.root.join(rel))
+}
+
+/// Drop the given nodes and shift the indices of the remaining ones. Returns
+/// the new indices of nodes that linked to a removed node.
+fn remove_nodes(data: &mut GraphData, removed: &BTreeSet<usize>) -> Vec<usize> {
+    let n = data.graph.nodes.len();
+    let mut new_index = vec![None; n];
+    let mut next = 0;
+    for (i, slot) in new_index.iter_mut().enumerate() {
+        if !removed.contains(&i) {
+            *slot = Some(next);
+            next += 1;
+        }
+    }
+    for &i in removed {
+        data.mentions
+            .remove(i, &data.contents[i], &data.explicit[i]);
+    }
+    data.mentions.remap(&new_index);
+    data.by_path.retain(|_, i| match new_index[*i] {
+        Some(new) => {
+            *i = new;
+            true
+        }
+        None => false,
+    });
+    let keep = |i: &usize| !removed.contains(i);
+    let mut idx = 0..n;
+    data.graph.nodes.retain(|_| keep(&idx.next().unwrap()));
+    let mut idx = 0..n;
+    data.canonical.retain(|_| keep(&idx.next().unwrap()));
+    let mut idx = 0..n;
+    data.contents.retain(|_| keep(&idx.next().unwrap()));
+    let mut idx = 0..n;
+    data.explicit.retain(|_| keep(&idx.next().unwrap()));
+    let mut idx = 0..n;
+    data.outgoing.retain(|_| keep(&idx.next().unwrap()));
+
+    let mut affected = Vec::new();
+    for (i, edges) in data.outgoing.iter_mut().enumerate() {
+        let before = edges.len();
+        edges.retain(|e| new_index[e.to].is_some());
+        if edges.len() != before {
+            affected.push(i);
+        }
+        for edge in edges.iter_mut() {
+            edge.from = i;
+            edge.to = new_index[edge.to].unwrap();
+        }
+    }
+    affected
+}
+
+/// Bring `data` up to date with the vault on disk.
+///
+/// The vault is walked to find new and deleted files and files whose
+/// modification time changed; everything else is left alone. See
+/// [`update_paths`] for how those files are processed. Notes in folders
+/// that cannot be read any more are kept as they were.
+pub fn refresh(data: &mut GraphData) -> Result<Changes> {
+    let walked = walk(&data.root, &config().ignore)?;
+    let present: HashSet<&PathBuf> = walked.files.iter().collect();
+    let mut paths: Vec<PathBuf> = data
+        .files
+        .keys()
+        .filter(|p| !present.contains(p) && !walked.is_skipped(p))
+        .cloned()
+        .collect();
+    for path in &walked.files {
+        match data.files.get(path) {
+            Some(state) if state.modified == modified_time(path) => {}
+            _ => paths.push(path.clone()),
+        }
+    }
+    data.skipped = walked.skipped.into_iter().map(|(_, e)| e).collect();
+    update_paths(data, &paths)
+}
+
+/// Map reported paths to the vault files they concern. A folder stands for
+/// every file below it: those on disk if it exists, else those it used to
+/// contain. Folders below them that cannot be read are added to
+/// [`GraphData::skipped`].
+fn expand_paths(data: &mut GraphData, paths: &[PathBuf]) -> Result<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    for path in paths {
+        let Some(path) = vault_path(data, path) else {
+            continue;
+        };
+        if path.is_dir() {
+            let walked = walk(&path, &config().ignore)?;
+            files.extend(walked.files);
+            data.skipped
+                .extend(walked.skipped.into_iter().map(|(_, e)| e));
+        } else if !path.exists() && !data.files.contains_key(&path) {
+            files.extend(data.files.keys().filter(|p| p.starts_with(&path)).cloned());
+        } else {
+            files.insert(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Update `data` for files that were created, modified or deleted.
+///
+/// A modified file is only re-scanned if its contents hash differently from
+/// the last read. Edges are recomputed for the nodes that were re-scanned and
+/// for nodes whose text mentions a name or alias that was added, removed or
+/// changed; all other edges are kept. Those nodes are looked up by the words
+/// of the name and the targets of their links, without reading every note's
+/// text.
+///
+/// Paths may be absolute or relative, and may name folders, as reported by
+/// a [`VaultWatcher`](crate::watch::VaultWatcher).
+pub fn update_paths(data: &mut GraphData, paths: &[PathBuf]) -> Result<Changes> {
+    let mut changes = Changes::default();
+    let mut dirty: BTreeSet<usize> = BTreeSet::new();
+    let mut added: BTreeSet<usize> = BTreeSet::new();
+    let mut removed: BTreeSet<usize> = BTreeSet::new();
+    // normalized names and aliases that appeared or disappeared
+    let mut changed_names: Vec<String> = Vec::new();
+    // nodes added by this update, which `by_canonical` does not list yet
+    let mut new_nodes: HashMap<(PathBuf, String), usize> = HashMap::new();
+
+    for path in expand_paths(data, paths)? {
+        let exists = path.is_file();
+        match data.files.get(&path).copied() {
+            Some(state) if exists => {
+                let (new_state, _) = read_file(&path)?;
+                data.files.insert(path.clone(), new_state);
+                if new_state.hash != state.hash
+                    && let Some(&i) = data.by_path.get(&path)
+                {
+                    dirty.insert(i);
+                }
+            }
+            Some(_) => {
+                data.files.remove(&path);
+                if let Some(i) = data.by_path.remove(&path) {
+                    let node = &mut data.graph.nodes[i];
+                    node.paths.retain(|p| p != &path);
+                    if node.paths.is_empty() {
+                        removed.insert(i);
+                        changed_names.push(normalize(&node.name));
+                        changed_names.extend(node.aliases.iter().map(|a| normalize(a)));
+                    } else {
+                        dirty.insert(i);
                     }
                 }
-                data.contents[idx] = normalize(&text);
+            }
+            None if exists => {
+                let Some((folder, stem)) = split_rel(&data.root, &path) else {
+                    continue;
+                };
+                let key = (folder, canonicalize(stem));
+                let existing = data
+                    .by_canonical
+                    .get(&key.1)
+                    .and_then(|nodes| {
+                        nodes
+                            .iter()
+                            .copied()
+                            .find(|&i| data.graph.nodes[i].folder() == key.0)
+                    })
+                    .or_else(|| new_nodes.get(&key).copied());
+                let i = match existing {
+                    Some(i) => i,
+                    None => {
+                        changed_names.push(normalize(stem));
+                        let i = push_node(data, &key.0, stem);
+                        new_nodes.insert(key, i);
+                        added.insert(i);
+                        i
+                    }
+                };
+                removed.remove(&i);
+                data.graph.nodes[i].paths.push(path.clone());
+                data.by_path.insert(path, i);
+                dirty.insert(i);
+            }
+            None => {}
+        }
+    }
+
+    for &i in &dirty {
+        if removed.contains(&i) {
+            continue;
+        }
+        if let Some(old) = reload_node(data, i)? {
+            changed_names.extend(old.iter().map(|a| normalize(a)));
+            let new = &data.graph.nodes[i].aliases;
+            changed_names.extend(new.iter().map(|a| normalize(a)));
+        }
+        let rel_path = data.graph.nodes[i].rel_path.clone();
+        if added.contains(&i) {
+            changes.added.push(rel_path);
+        } else {
+            changes.modified.push(rel_path);
+        }
+    }
+    for &i in &removed {
+        changes.removed.push(data.graph.nodes[i].rel_path.clone());
+    }
+
+    let mut dirty: BTreeSet<usize> = dirty
+        .into_iter()
+        .filter(|i| !removed.contains(i))
+        .map(|i| i - removed.range(..i).count())
+        .collect();
+    if !removed.is_empty() {
+        dirty.extend(remove_nodes(data, &removed));
+    }
+
+    changed_names.retain(|n| !n.is_empty());
+    changed_names.sort();
+    changed_names.dedup();
+    if !changed_names.is_empty() {
+        rebuild_names(data);
+        for name in &changed_names {
+            let mentioning = data.mentions.candidates(name);
+            dirty.extend(
+                mentioning
+                    .into_iter()
+                    .filter(|&i| data.contents[i].contains(name.as_str())),
+            );
+            if let Some(linking) = data.mentions.links.get(&name.replace(' ', "")) {
+                dirty.extend(linking.iter().copied());
             }
         }
     }
-    recompute_edges(data);
+    recompute_edges_of(data, dirty);
+    Ok(changes)
+}
+
+/// Re-scan the notes open in the editor, given by path or title. Only notes
+/// whose files changed are read again.
+pub fn update_open_notes(data: &mut GraphData, open_notes: &[String]) -> Result<Changes> {
+    let mut paths = Vec::new();
+    for name in open_notes {
+        let path = Path::new(name);
+        let by_path = data.by_path.get(path).copied();
+        let by_name = || {
+            let stem = path.file_stem().and_then(|s| s.to_str())?;
+            data.by_canonical.get(&canonicalize(stem))?.first().copied()
+        };
+        if let Some(idx) = by_path.or_else(by_name) {
+            let node = &data.graph.nodes[idx];
+            paths.extend(
+                node.paths
+                    .iter()
+                    .filter(|p| {
+                        data.files
+                            .get(*p)
+                            .is_none_or(|state| state.modified != modified_time(p))
+                    })
+                    .cloned(),
+            );
+        }
+    }
+    update_paths(data, &paths)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{canonicalize, find_unique_links, normalize};
+    use super::{
+        Graph, GraphData, LinkKind, LinkMode, Matcher, Node, canonicalize, load_from, normalize,
+        open, rebuild_names, recompute_edges, refresh,
+    };
+    use crate::links::parse_links;
+    use crate::testing::TempDir;
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn graph_data(notes: &[(&str, &str, &[&str])]) -> GraphData {
+        graph_data_with_mode(notes, LinkMode::Both)
+    }
+
+    /// Build graph data from `(relative path, text, aliases)` triples.
+    fn graph_data_with_mode(notes: &[(&str, &str, &[&str])], mode: LinkMode) -> GraphData {
+        let name = |path: &str| path.rsplit('/').next().unwrap().to_string();
+        let nodes = notes
+            .iter()
+            .map(|(path, _, aliases)| Node {
+                name: name(path),
+                rel_path: path.into(),
+                paths: Vec::new(),
+                aliases: aliases.iter().map(|a| a.to_string()).collect(),
+                tags: Vec::new(),
+                links: 0,
+            })
+            .collect();
+        let mut data = GraphData {
+            graph: Graph {
+                nodes,
+                ..Graph::default()
+            },
+            canonical: notes
+                .iter()
+                .map(|(path, _, _)| canonicalize(&name(path)))
+                .collect(),
+            contents: notes.iter().map(|(_, text, _)| normalize(text)).collect(),
+            explicit: notes
+                .iter()
+                .map(|(_, text, _)| parse_links(text).into_iter().map(|l| l.target).collect())
+                .collect(),
+            mode,
+            ..GraphData::default()
+        };
+        rebuild_names(&mut data);
+        recompute_edges(&mut data);
+        data
+    }
 
     #[test]
     fn longest_match() {
-        let names = vec![
+        let names = [
             "nuclear power".to_string(),
             "nuclear power in iran".to_string(),
         ];
-        let canonical: Vec<String> = names.iter().map(|s| canonicalize(s)).collect();
         let normalized: Vec<String> = names.iter().map(|s| normalize(s)).collect();
         let text = normalize("nuclear power in Iran");
-        let links = find_unique_links(&text, &canonical, &normalized);
+        let links = Matcher::new(&normalized).find(&text);
         assert_eq!(links, vec![1]);
     }
 
     #[test]
     fn partial_overlap() {
-        let names = vec![
+        let names = [
             "Power Generation Techniques".to_string(),
             "Nuclear Power Generation".to_string(),
         ];
-        let canonical: Vec<String> = names.iter().map(|s| canonicalize(s)).collect();
         let normalized: Vec<String> = names.iter().map(|s| normalize(s)).collect();
         let text = normalize("nuclear power generation techniques");
-        let mut links = find_unique_links(&text, &canonical, &normalized);
+        let mut links = Matcher::new(&normalized).find(&text);
         links.sort();
         assert_eq!(links, vec![0, 1]);
     }
 
     #[test]
     fn no_substring_match() {
-        let names = vec!["note".to_string(), "another note".to_string()];
-        let canonical: Vec<String> = names.iter().map(|s| canonicalize(s)).collect();
+        let names = ["note".to_string(), "another note".to_string()];
         let normalized: Vec<String> = names.iter().map(|s| normalize(s)).collect();
         let text = normalize("newnote another note with spaces");
-        let mut links = find_unique_links(&text, &canonical, &normalized);
+        let mut links = Matcher::new(&normalized).find(&text);
         links.sort();
         assert_eq!(links, vec![1]);
     }
+
+    #[test]
+    fn repeated_names() {
+        let names = ["power", "nuclear power", "power"].map(normalize);
+        let text = normalize("Nuclear power is power.");
+        assert_eq!(Matcher::new(&names).find(&text), [0, 1, 2]);
+        let text = normalize("Nuclear power only.");
+        assert_eq!(Matcher::new(&names).find(&text), [1]);
+    }
+
+    #[test]
+    fn alias_links() {
+        let data = graph_data(&[
+            ("Nuclear Power", "", &["Fission Power"]),
+            ("Energy", "fission power plants", &[]),
+            ("Grid", "nuclear power and fission power", &[]),
+            ("Policy", "fission power stations in iran", &["Iran"]),
+        ]);
+        let edges: Vec<_> = data
+            .graph
+            .edges
+            .iter()
+            .map(|e| (e.from, e.to, e.alias.as_deref()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                (1, 0, Some("Fission Power")),
+                (2, 0, None),
+                (3, 0, Some("Fission Power")),
+            ]
+        );
+        assert_eq!(data.graph.nodes[0].links, 3);
+    }
+
+    #[test]
+    fn explicit_links_and_modes() {
+        let notes: &[(&str, &str, &[&str])] = &[
+            ("Reactor", "see [[Fuel|the fuel]] and the turbine", &[]),
+            ("Fuel", "", &["Uranium"]),
+            (
+                "Turbine",
+                "[spinning](Reactor.md) and [[uranium#Isotopes]]",
+                &[],
+            ),
+        ];
+        let kinds = |mode| {
+            graph_data_with_mode(notes, mode)
+                .graph
+                .edges
+                .iter()
+                .map(|e| (e.from, e.to, e.kind))
+                .collect::<Vec<_>>()
+        };
+        use LinkKind::{Explicit, Implicit};
+        assert_eq!(
+            kinds(LinkMode::Both),
+            vec![
+                (0, 1, Explicit),
+                (0, 2, Implicit),
+                (2, 0, Explicit),
+                (2, 1, Explicit)
+            ]
+        );
+        assert_eq!(
+            kinds(LinkMode::Explicit),
+            vec![(0, 1, Explicit), (2, 0, Explicit), (2, 1, Explicit)]
+        );
+        assert_eq!(
+            kinds(LinkMode::Implicit),
+            vec![
+                (0, 1, Implicit),
+                (0, 2, Implicit),
+                (2, 0, Implicit),
+                (2, 1, Implicit)
+            ]
+        );
+        let edge = &graph_data(notes).graph.edges[3];
+        assert_eq!(edge.alias.as_deref(), Some("Uranium"));
+    }
+
+    #[test]
+    fn same_name_in_different_folders() {
+        let data = graph_data(&[
+            ("Plan", "", &[]),
+            ("work/Plan", "", &[]),
+            ("work/Notes", "the plan", &[]),
+            ("home/Notes", "the plan, see [[work/plan]]", &[]),
+            (
+                "home/deep/Todo",
+                "[x](../../work/Plan.md) and [[Notes]]",
+                &[],
+            ),
+        ]);
+        assert_eq!(data.graph.nodes.len(), 5);
+        let edges: Vec<_> = data
+            .graph
+            .edges
+            .iter()
+            .map(|e| (e.from, e.to, e.kind))
+            .collect();
+        use LinkKind::{Explicit, Implicit};
+        assert_eq!(
+            edges,
+            vec![
+                (2, 1, Implicit),
+                (3, 0, Implicit),
+                (3, 1, Explicit),
+                (4, 0, Implicit),
+                (4, 1, Explicit),
+                (4, 3, Explicit),
+            ]
+        );
+    }
+
+    #[test]
+    fn refresh_updates_changed_notes() {
+        let root = TempDir::new("graph_refresh");
+        fs::write(root.join("Alpha.md"), "links to beta").unwrap();
+        fs::write(root.join("Beta.md"), "nothing here").unwrap();
+        fs::write(root.join("Gamma.md"), "mentions delta").unwrap();
+
+        let mut data = load_from(&root).unwrap();
+        let names = |data: &GraphData| -> Vec<String> {
+            data.graph.nodes.iter().map(|n| n.name.clone()).collect()
+        };
+        let edges = |data: &GraphData| -> Vec<(String, String)> {
+            let nodes = &data.graph.nodes;
+            data.graph
+                .edges
+                .iter()
+                .map(|e| (nodes[e.from].name.clone(), nodes[e.to].name.clone()))
+                .collect()
+        };
+        assert_eq!(edges(&data), [("Alpha".into(), "Beta".into())]);
+        assert!(refresh(&mut data).unwrap().is_empty());
+
+        // a new note picks up mentions in notes that did not change
+        fs::write(root.join("Delta.md"), "back to alpha").unwrap();
+        let changes = refresh(&mut data).unwrap();
+        assert_eq!(changes.added, [PathBuf::from("Delta")]);
+        assert!(changes.modified.is_empty());
+        assert_eq!(
+            edges(&data),
+            [
+                ("Alpha".into(), "Beta".into()),
+                ("Gamma".into(), "Delta".into()),
+                ("Delta".into(), "Alpha".into()),
+            ]
+        );
+
+        // removing a note shifts the indices of later ones
+        fs::remove_file(root.join("Beta.md")).unwrap();
+        let changes = refresh(&mut data).unwrap();
+        assert_eq!(changes.removed, [PathBuf::from("Beta")]);
+        assert_eq!(names(&data), ["Alpha", "Gamma", "Delta"]);
+        assert_eq!(
+            edges(&data),
+            [
+                ("Gamma".into(), "Delta".into()),
+                ("Delta".into(), "Alpha".into()),
+            ]
+        );
+        assert_eq!(data.graph.nodes[0].links, 1);
+
+        // rewriting the same content is not a change
+        fs::write(root.join("Gamma.md"), "mentions delta").unwrap();
+        let changes = refresh(&mut data).unwrap();
+        assert!(changes.modified.is_empty());
+
+        fs::write(root.join("Gamma.md"), "mentions alpha").unwrap();
+        let changes = refresh(&mut data).unwrap();
+        assert_eq!(changes.modified, [PathBuf::from("Gamma")]);
+        assert_eq!(
+            edges(&data),
+            [
+                ("Gamma".into(), "Alpha".into()),
+                ("Delta".into(), "Alpha".into()),
+            ]
+        );
+
+        // notes that did not change find new names through the word and link
+        // lookups, which end up as a fresh load would build them
+        fs::write(root.join("Alpha.md"), "see [[Epsilon]] and the gamma ray").unwrap();
+        refresh(&mut data).unwrap();
+        fs::write(root.join("Gamma Ray.md"), "").unwrap();
+        fs::write(root.join("Epsilon.md"), "").unwrap();
+        fs::remove_file(root.join("Delta.md")).unwrap();
+        refresh(&mut data).unwrap();
+        let fresh = load_from(&root).unwrap();
+        let sorted = |data: &GraphData| {
+            let mut edges = edges(data);
+            edges.sort();
+            edges
+        };
+        assert_eq!(
+            sorted(&data),
+            [
+                ("Alpha".into(), "Epsilon".into()),
+                ("Alpha".into(), "Gamma Ray".into()),
+                ("Gamma".into(), "Alpha".into()),
+            ]
+        );
+        assert_eq!(sorted(&data), sorted(&fresh));
+        let by_name = |data: &GraphData| {
+            let name = |i: &usize| data.graph.nodes[*i].name.clone();
+            let lookup =
+                |map: &HashMap<String, HashSet<usize>>| -> BTreeMap<String, BTreeSet<String>> {
+                    map.iter()
+                        .map(|(k, nodes)| (k.clone(), nodes.iter().map(name).collect()))
+                        .collect()
+                };
+            let paths: BTreeMap<PathBuf, String> = data
+                .by_path
+                .iter()
+                .map(|(p, i)| (p.clone(), name(i)))
+                .collect();
+            (
+                paths,
+                lookup(&data.mentions.words),
+                lookup(&data.mentions.links),
+            )
+        };
+        assert_eq!(by_name(&data), by_name(&fresh));
+    }
+
+    #[test]
+    fn index_cache() {
+        let root = TempDir::new("graph_cache");
+        fs::write(root.join("Alpha.md"), "links to beta").unwrap();
+        fs::write(root.join("Beta.md"), "nothing here").unwrap();
+        let index = root.join(".notes/index");
+        let edges = |data: &GraphData| -> Vec<(usize, usize)> {
+            data.graph.edges.iter().map(|e| (e.from, e.to)).collect()
+        };
+
+        let data = open(&root).unwrap();
+        assert!(index.is_file());
+        assert_eq!(edges(&data), [(0, 1)]);
+
+        // the cache is picked up and checked against the files
+        fs::write(root.join("Beta.md"), "back to alpha").unwrap();
+        let data = open(&root).unwrap();
+        assert_eq!(data.graph.nodes.len(), 2);
+        assert_eq!(edges(&data), [(0, 1), (1, 0)]);
+        assert_eq!(data.graph.nodes[0].paths, [root.join("Alpha.md")]);
+
+        // a corrupt cache is rebuilt
+        fs::write(&index, "{ not json").unwrap();
+        let data = open(&root).unwrap();
+        assert_eq!(edges(&data), [(0, 1), (1, 0)]);
+        assert!(fs::read_to_string(&index).unwrap().starts_with("{\""));
+    }
 }
@@ -1,2 +1,24 @@
+pub mod anchors;
+mod cache;
+pub mod config;
+pub mod editor;
+pub mod error;
+pub mod frontmatter;
 pub mod graph;
+pub mod links;
+pub mod list;
 pub mod note;
+pub mod periodic;
+pub mod references;
+pub mod rename;
+pub mod render;
+pub mod search;
+pub mod tags;
+pub mod tasks;
+#[cfg(test)]
+mod testing;
+pub mod trash;
+pub mod vault;
+pub mod watch;
+
+pub use error::{Error, Result};
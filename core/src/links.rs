@@ -0,0 +1,185 @@
+//! Explicit links written in note text.
+//!
+//! Both Obsidian style wikilinks (`[[Target]]`, `[[Target|label]]`,
+//! `[[Target#Heading]]`, `![[Target]]`) and Markdown links to local files
+//! (`[text](Target.md)`) are recognised. Links inside fenced code blocks and
+//! inline code are ignored.
+
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Note the link points at, as written (without heading or extension)
+    pub target: String,
+    /// Heading or block reference after `#`, if any
+    pub anchor: Option<String>,
+    /// Display text, if it differs from the target
+    pub label: Option<String>,
+    /// Whether the link is an embed (`![[...]]`)
+    pub embed: bool,
+    /// Byte range of the whole link in the source text
+    pub span: Range<usize>,
+}
+
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+            && let Ok(b) = u8::from_str_radix(&s[i + 1..i + 3], 16)
+        {
+            out.push(b);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(out).unwrap_or_else(|_| s.to_string())
+}
+
+fn split_anchor(target: &str) -> (String, Option<String>) {
+    match target.split_once('#') {
+        Some((t, a)) => (t.trim().to_string(), Some(a.trim().to_string())),
+        None => (target.trim().to_string(), None),
+    }
+}
+
+fn wikilink(inner: &str, embed: bool, span: Range<usize>) -> Option<Link> {
+    let (target, label) = match inner.split_once('|') {
+        Some((t, l)) => (t, Some(l.trim().to_string())),
+        None => (inner, None),
+    };
+    let (target, anchor) = split_anchor(target);
+    if target.is_empty() && anchor.is_none() {
+        return None;
+    }
+    Some(Link {
+        target,
+        anchor,
+        label,
+        embed,
+        span,
+    })
+}
+
+fn markdown_link(label: &str, dest: &str, span: Range<usize>) -> Option<Link> {
+    let dest = dest.trim();
+    let dest = dest
+        .strip_prefix('<')
+        .and_then(|d| d.strip_suffix('>'))
+        .unwrap_or_else(|| dest.split_whitespace().next().unwrap_or_default());
+    if dest.is_empty() || dest.starts_with('#') || dest.contains(':') {
+        // in-page anchors, URLs and mailto links
+        return None;
+    }
+    let (path, anchor) = split_anchor(&percent_decode(dest));
+    let name = path.strip_suffix(".md").unwrap_or(&path).to_string();
+    Some(Link {
+        target: name,
+        anchor,
+        label: Some(label.to_string()).filter(|l| !l.is_empty()),
+        embed: false,
+        span,
+    })
+}
+
+/// Find all explicit links in `text`, in order of appearance.
+pub fn parse_links(text: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut in_fence = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        parse_line(line, line_start, &mut links);
+    }
+    links
+}
+
+fn parse_line(line: &str, base: usize, links: &mut Vec<Link>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    let mut in_code = false;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'`' => in_code = !in_code,
+            b'[' if !in_code && bytes.get(i + 1) == Some(&b'[') => {
+                if let Some(len) = line[i + 2..].find("]]") {
+                    let embed = i > 0 && bytes[i - 1] == b'!';
+                    let start = if embed { i - 1 } else { i };
+                    let end = i + 2 + len + 2;
+                    let inner = &line[i + 2..i + 2 + len];
+                    if let Some(link) = wikilink(inner, embed, base + start..base + end) {
+                        links.push(link);
+                    }
+                    i = end;
+                    continue;
+                }
+            }
+            b'[' if !in_code => {
+                if let Some(close) = line[i + 1..].find(']') {
+                    let label_end = i + 1 + close;
+                    if line[label_end + 1..].starts_with('(')
+                        && let Some(len) = line[label_end + 2..].find(')')
+                    {
+                        let end = label_end + 2 + len + 1;
+                        let image = i > 0 && bytes[i - 1] == b'!';
+                        let label = &line[i + 1..label_end];
+                        let dest = &line[label_end + 2..end - 1];
+                        if !image
+                            && let Some(link) = markdown_link(label, dest, base + i..base + end)
+                        {
+                            links.push(link);
+                        }
+                        i = end;
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_links;
+
+    #[test]
+    fn wikilinks_and_markdown_links() {
+        let text = "See [[Nuclear Power]], [[Iran|the country]] and [[Physics#Fission]].\n\
+                    Also [notes](Other%20Note.md#Intro), [web](https://example.com) and ![[chart.png]].\n\
+                    ```\n[[Not A Link]]\n```\n`[[Nor This]]`\n";
+        let links = parse_links(text);
+        let targets: Vec<_> = links.iter().map(|l| l.target.as_str()).collect();
+        assert_eq!(
+            targets,
+            [
+                "Nuclear Power",
+                "Iran",
+                "Physics",
+                "Other Note",
+                "chart.png"
+            ]
+        );
+        assert_eq!(links[1].label.as_deref(), Some("the country"));
+        assert_eq!(links[2].anchor.as_deref(), Some("Fission"));
+        assert_eq!(links[3].anchor.as_deref(), Some("Intro"));
+        assert!(links[4].embed);
+        assert_eq!(&text[links[0].span.clone()], "[[Nuclear Power]]");
+        assert_eq!(&text[links[4].span.clone()], "![[chart.png]]");
+    }
+}
@@ -0,0 +1,123 @@
+//! Listing the notes of a vault, as used by `notes list`.
+
+use std::cmp::Reverse;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+
+use crate::graph::{Graph, Node};
+use crate::tags::tag_matches;
+
+/// Order of listed notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// Alphabetically by name, ignoring case
+    #[default]
+    Name,
+    /// Most recently modified first
+    Modified,
+    /// Most linked first
+    Links,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "name" => Ok(SortKey::Name),
+            "mtime" | "modified" => Ok(SortKey::Modified),
+            "links" => Ok(SortKey::Links),
+            _ => Err(format!(
+                "unknown sort key '{}', expected name, mtime or links",
+                s
+            )),
+        }
+    }
+}
+
+/// Which notes to list. Every criterion that is set must match.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Only notes with a file in this format, e.g. `pdf`
+    pub format: Option<String>,
+    /// Only notes in this folder or below it, relative to the vault
+    pub folder: Option<PathBuf>,
+    /// Only notes with a tag matching this pattern, e.g. `area/*` (see
+    /// [`tag_matches`])
+    pub tag: Option<String>,
+}
+
+impl Filter {
+    pub fn matches(&self, node: &Node) -> bool {
+        if let Some(format) = &self.format {
+            let format = format.trim_start_matches('.').to_ascii_lowercase();
+            if !node.formats().contains(&format) {
+                return false;
+            }
+        }
+        if let Some(folder) = &self.folder
+            && !node.folder().starts_with(folder)
+        {
+            return false;
+        }
+        if let Some(pattern) = &self.tag
+            && !node.tags.iter().any(|t| tag_matches(t, pattern))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// One listed note.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    pub name: String,
+    /// Path relative to the vault without extension
+    pub path: PathBuf,
+    pub formats: Vec<String>,
+    /// Latest modification of any of the note's files, in seconds since the
+    /// Unix epoch
+    pub modified: Option<u64>,
+    pub links: usize,
+    pub aliases: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+impl Entry {
+    fn new(node: &Node) -> Self {
+        Entry {
+            name: node.name.clone(),
+            path: node.rel_path.clone(),
+            formats: node.formats(),
+            modified: node
+                .modified()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            links: node.links,
+            aliases: node.aliases.clone(),
+            tags: node.tags.clone(),
+        }
+    }
+}
+
+/// The notes of `graph` that pass `filter`, in the order given by `sort`.
+/// Ties are broken by path so the output is stable.
+pub fn list_notes(graph: &Graph, filter: &Filter, sort: SortKey) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = graph
+        .nodes
+        .iter()
+        .filter(|node| filter.matches(node))
+        .map(Entry::new)
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    match sort {
+        SortKey::Name => entries.sort_by_key(|e| e.name.to_lowercase()),
+        SortKey::Modified => entries.sort_by_key(|e| Reverse(e.modified)),
+        SortKey::Links => entries.sort_by_key(|e| Reverse(e.links)),
+    }
+    entries
+}
@@ -1,93 +1,1384 @@
+use std::collections::HashMap;
 use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::{self, Command};
 
-use notes_core::note::Note;
+use chrono::{Local, NaiveDate};
+use clap::{Args, ColorChoice, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::env::{Bash, EnvCompleter, Fish, Zsh};
+use clap_complete::{ArgValueCandidates, CompleteEnv, CompletionCandidate};
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+use notes_core::Error;
+use notes_core::anchors::{dangling_anchors, resolve_anchor};
+use notes_core::config::{Config, set_config};
+use notes_core::editor::{Template, create_from_template, create_note, edit, list_templates};
+use notes_core::graph::LinkKind;
+use notes_core::graph::{GraphData, load_graph_data, save_index, update_paths};
+use notes_core::list::{Entry, Filter, SortKey, list_notes};
+use notes_core::note::{Note, read_text, set_vault_dir, vault_dir};
+use notes_core::periodic::{Period, open_periodic};
+use notes_core::references::{Reference, ReferenceKind, backlinks, outgoing, unlinked_mentions};
+use notes_core::rename::{RenameOptions, apply, plan_rename, recover};
+use notes_core::render::{DEFAULT_MAX_DEPTH, RenderOptions, render};
+use notes_core::search::{Hit, Query, SearchIndex, Snippet};
+use notes_core::tags::{TagCount, tag_tree};
+use notes_core::tasks::{Status, Task, TaskFilter, collect_tasks, complete_task};
+use notes_core::trash::{TrashEntry, empty_trash, find_entry, list_trash, restore, trash_node};
+use notes_core::vault::{VaultLocation, find_vault};
+use notes_core::watch::VaultWatcher;
+
+/// Exit codes, following the BSD `sysexits.h` convention.
+mod exit {
+    /// The command line could not be parsed
+    pub const USAGE: i32 = 64;
+    /// Input such as a title or a note's contents is not usable
+    pub const DATA_ERR: i32 = 65;
+    /// A note or the vault does not exist
+    pub const NO_INPUT: i32 = 66;
+    /// An external program (editor, GUI) could not be run
+    pub const UNAVAILABLE: i32 = 69;
+    /// A file that would be created already exists
+    pub const CANT_CREATE: i32 = 73;
+    /// Reading or writing a file failed
+    pub const IO_ERR: i32 = 74;
+    /// Access to a file was denied
+    pub const NO_PERM: i32 = 77;
+    /// The configuration is invalid
+    pub const CONFIG: i32 = 78;
+}
+
+/// Plain Markdown notes with a link graph.
+#[derive(Parser)]
+#[command(name = "notes", version)]
+struct Cli {
+    /// Vault directory [default: $NOTES_VAULT, else the closest folder
+    /// containing .notes/, else `vault` in the user config, else ./notes]
+    #[arg(long, global = true, value_name = "DIR")]
+    vault: Option<PathBuf>,
+
+    /// Read settings from this TOML file
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Only print errors and the output that was asked for
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// When to use colors
+    #[arg(long, global = true, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Create a new note, from the default template if there is one
+    New {
+        #[arg(required = true, value_name = "TITLE")]
+        title: Vec<String>,
+        /// Fill the note in from this template in the templates folder
+        #[arg(short, long, value_name = "NAME", add = ArgValueCandidates::new(template_names))]
+        template: Option<String>,
+        /// Value of a template variable, asked for if not given
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+    },
+    /// Show the contents of a note
+    Show {
+        #[arg(required = true, value_name = "TITLE", add = ArgValueCandidates::new(note_titles))]
+        title: Vec<String>,
+        /// Expand `![[Note]]` embeds
+        #[arg(long)]
+        render: bool,
+        /// Levels of embeds inside embeds to expand
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_DEPTH)]
+        max_depth: usize,
+    },
+    /// Write a note as Markdown with its embeds expanded
+    Export {
+        #[arg(required = true, value_name = "TITLE", add = ArgValueCandidates::new(note_titles))]
+        title: Vec<String>,
+        /// File to write to instead of stdout; links to embedded files are
+        /// made relative to its folder
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Levels of embeds inside embeds to expand
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_DEPTH)]
+        max_depth: usize,
+    },
+    /// Open a note in $VISUAL or $EDITOR, creating it if needed
+    Edit {
+        #[arg(required = true, value_name = "TITLE", add = ArgValueCandidates::new(note_titles))]
+        title: Vec<String>,
+    },
+    /// Open today's daily note, creating it if needed
+    Today {
+        /// Print the path of the note instead of opening it
+        #[arg(long)]
+        no_edit: bool,
+    },
+    /// Open a daily note, e.g. `notes daily --offset -1` for yesterday's
+    Daily(PeriodArgs),
+    /// Open the note of a week, starting on Monday
+    Weekly(PeriodArgs),
+    /// Open the note of a month
+    Monthly(PeriodArgs),
+    /// Show the notes a note links to, with the lines that link
+    Links {
+        #[arg(required = true, value_name = "TITLE", add = ArgValueCandidates::new(note_titles))]
+        title: Vec<String>,
+        /// Print the links as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the notes that link to or mention a note, with the lines that do
+    Backlinks {
+        #[arg(required = true, value_name = "TITLE", add = ArgValueCandidates::new(note_titles))]
+        title: Vec<String>,
+        /// Print the backlinks as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Rename a note and update the links to it
+    Mv(MvArgs),
+    /// Move a note with all its files to the trash
+    Rm {
+        #[arg(required = true, value_name = "TITLE", add = ArgValueCandidates::new(note_titles))]
+        title: Vec<String>,
+        /// Do not ask for confirmation when other notes link to it
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// List, restore or empty the trash
+    #[command(subcommand)]
+    Trash(TrashCommand),
+    /// List notes
+    List(ListArgs),
+    /// Search notes, e.g. `notes search "road map" tag:work`
+    Search(SearchArgs),
+    /// Report links to headings or blocks that do not exist
+    Check {
+        /// Print the dangling links as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the tags of all notes as a tree with the number of notes
+    Tags {
+        /// Print the tree as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the checkboxes of all notes, e.g. `notes tasks --due today`
+    Tasks(TasksArgs),
+    /// Follow changes to the vault as they happen
+    Watch,
+    /// Launch the graphical interface
+    Gui,
+    /// Print the vault in use and how it was found
+    Vault,
+    /// Print a shell completion script, e.g. `source <(notes completions bash)`
+    Completions {
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+}
+
+#[derive(Args)]
+struct ListArgs {
+    /// Sort by name, mtime or links
+    #[arg(long, value_name = "KEY", default_value = "name")]
+    sort: SortKey,
+
+    /// Reverse the order
+    #[arg(long)]
+    reverse: bool,
+
+    /// Only notes with a file of this type, e.g. pdf
+    #[arg(long = "type", value_name = "EXT")]
+    file_type: Option<String>,
+
+    /// Only notes in this folder or below it
+    #[arg(long, value_name = "DIR")]
+    folder: Option<PathBuf>,
+
+    /// Only notes with this tag, or with `area/*` a tag below `area`
+    #[arg(long, value_name = "TAG")]
+    tag: Option<String>,
+
+    /// Output format
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
+
+    /// Shorthand for --format json
+    #[arg(long, conflicts_with = "format")]
+    json: bool,
+}
+
+#[derive(Args)]
+struct PeriodArgs {
+    /// Periods before (negative) or after the current one
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        allow_negative_numbers = true
+    )]
+    offset: i32,
+
+    /// Count from this day instead of today, e.g. 2026-10-18
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    date: Option<NaiveDate>,
+
+    /// Print the path of the note instead of opening it
+    #[arg(long)]
+    no_edit: bool,
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct TasksArgs {
+    #[command(subcommand)]
+    command: Option<TasksCommand>,
+
+    /// Only open or done tasks
+    #[arg(long, value_name = "STATUS")]
+    status: Option<Status>,
+
+    /// Only tasks due on or before this day: today, tomorrow or YYYY-MM-DD
+    #[arg(long, value_name = "DATE", value_parser = parse_day)]
+    due: Option<NaiveDate>,
+
+    /// Only tasks with this tag or a tag below it
+    #[arg(long)]
+    tag: Option<String>,
+
+    /// Print the tasks as JSON
+    #[arg(long)]
+    json: bool,
+}
 
-    if args.len() < 2 {
-        print_usage();
-        process::exit(1);
+#[derive(Subcommand)]
+enum TasksCommand {
+    /// Check a task's checkbox
+    Done {
+        /// Id of the task, as listed by `notes tasks`
+        id: String,
+    },
+}
+
+/// A day given as `today`, `tomorrow` or `YYYY-MM-DD`.
+fn parse_day(s: &str) -> Result<NaiveDate, String> {
+    let today = Local::now().date_naive();
+    match s {
+        "today" => Ok(today),
+        "tomorrow" => Ok(today.succ_opt().unwrap_or(today)),
+        _ => NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| {
+            format!(
+                "'{}' is not a day, expected today, tomorrow or YYYY-MM-DD",
+                s
+            )
+        }),
     }
+}
+
+#[derive(Subcommand)]
+enum TrashCommand {
+    /// List deleted notes, most recent first
+    List {
+        /// Print the entries as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Put a deleted note back where it was
+    Restore {
+        /// Id of the entry, or the name of the note
+        #[arg(required = true, value_name = "ENTRY")]
+        entry: Vec<String>,
+    },
+    /// Delete the notes in the trash for good
+    Empty {
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
 
-    let command = &args[1];
+#[derive(Args)]
+struct MvArgs {
+    /// Note to rename
+    #[arg(value_name = "OLD", add = ArgValueCandidates::new(note_titles))]
+    old: String,
 
-    match command.as_str() {
-        "new" => {
-            if args.len() < 3 {
-                println!("Error: Missing title for 'new' command.");
-                print_usage();
-                process::exit(1);
-            }
-            let title = args[2..].join(" "); // Allow titles with spaces
-            handle_new_note(&title);
-        }
-        "show" => {
-            if args.len() < 3 {
-                println!("Error: Missing title for 'show' command.");
-                print_usage();
-                process::exit(1);
+    /// New title, optionally with folders, e.g. "projects/Road map"
+    #[arg(value_name = "NEW")]
+    new: String,
+
+    /// Also replace plain-text mentions of the old name in notes that link
+    /// to it
+    #[arg(long)]
+    mentions: bool,
+
+    /// Only show what would change
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    /// Words, "quoted phrases" and tag:, path: or format: filters
+    #[arg(required = true, value_name = "QUERY")]
+    query: Vec<String>,
+
+    /// Show at most this many notes
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    limit: usize,
+
+    /// Print the hits as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Tsv,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Where messages go and how they look.
+struct Output {
+    quiet: bool,
+    /// Whether messages on stderr are colored
+    color: bool,
+    /// Whether matches in results on stdout are colored
+    highlight: bool,
+}
+
+impl Output {
+    fn new(quiet: bool, color: ColorChoice) -> Self {
+        let enabled = |terminal: bool| match color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => env::var_os("NO_COLOR").is_none() && terminal,
+        };
+        Output {
+            quiet,
+            color: enabled(io::stderr().is_terminal()),
+            highlight: enabled(io::stdout().is_terminal()),
+        }
+    }
+
+    /// Progress and confirmation messages, left out with `--quiet`.
+    fn info(&self, message: &str) {
+        if !self.quiet {
+            println!("{}", message);
+        }
+    }
+
+    /// Problems that do not stop the command, left out with `--quiet`.
+    fn warning(&self, message: &str) {
+        if self.quiet {
+            return;
+        }
+        if self.color {
+            eprintln!("\x1b[1;33mWarning:\x1b[0m {}", message);
+        } else {
+            eprintln!("Warning: {}", message);
+        }
+    }
+
+    fn error(&self, message: &str) {
+        if self.color {
+            eprintln!("\x1b[1;31mError:\x1b[0m {}", message);
+        } else {
+            eprintln!("Error: {}", message);
+        }
+    }
+}
+
+fn main() {
+    // answers completion requests from the scripts printed by `completions`
+    CompleteEnv::with_factory(Cli::command).complete();
+
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            process::exit(if e.use_stderr() { exit::USAGE } else { 0 });
+        }
+    };
+    let out = Output::new(cli.quiet, cli.color);
+
+    let location = match locate(&cli) {
+        Ok(location) => location,
+        Err(e) => {
+            out.error(&e.to_string());
+            process::exit(exit_code(&e));
+        }
+    };
+    set_vault_dir(&location.path);
+    // --config replaces the vault's own .notes/config.toml
+    let loaded = match &cli.config {
+        Some(path) => Config::load(path),
+        None => Config::for_vault(&location.path),
+    };
+    match loaded {
+        Ok((config, warnings)) => {
+            for warning in warnings {
+                out.warning(&warning);
             }
-            let title = args[2..].join(" "); // Allow titles with spaces
-            handle_show_note(&title);
+            set_config(config);
+        }
+        Err(e) => {
+            out.error(&e.to_string());
+            process::exit(exit_code(&e));
         }
-        "gui" => {
+    }
+
+    // words of a title may be passed unquoted
+    let title = match &cli.command {
+        Commands::New { title, .. }
+        | Commands::Show { title, .. }
+        | Commands::Export { title, .. }
+        | Commands::Edit { title }
+        | Commands::Links { title, .. }
+        | Commands::Backlinks { title, .. }
+        | Commands::Rm { title, .. } => title.join(" "),
+        Commands::Trash(TrashCommand::Restore { entry }) => entry.join(" "),
+        Commands::Mv(args) => args.old.clone(),
+        _ => String::new(),
+    };
+    // commands that change the vault first finish a rename cut short
+    let changes_vault = match &cli.command {
+        Commands::New { .. }
+        | Commands::Edit { .. }
+        | Commands::Today { .. }
+        | Commands::Daily(_)
+        | Commands::Weekly(_)
+        | Commands::Monthly(_)
+        | Commands::Mv(_)
+        | Commands::Rm { .. }
+        | Commands::Trash(TrashCommand::Restore { .. } | TrashCommand::Empty { .. }) => true,
+        Commands::Tasks(args) => args.command.is_some(),
+        _ => false,
+    };
+    if changes_vault && let Err(e) = recover(&vault_dir()) {
+        report_error(&out, &title, &location, &e);
+        process::exit(exit_code(&e));
+    }
+    let result = match cli.command {
+        Commands::New { template, vars, .. } => {
+            handle_new_note(&out, &title, template.as_deref(), vars)
+        }
+        Commands::Show {
+            render, max_depth, ..
+        } => handle_show_note(&out, &title, render.then_some(max_depth)),
+        Commands::Export {
+            output, max_depth, ..
+        } => handle_export(&out, &title, output.as_deref(), max_depth),
+        Commands::Edit { .. } => handle_edit_note(&out, &title),
+        Commands::Today { no_edit } => {
+            let args = PeriodArgs {
+                offset: 0,
+                date: None,
+                no_edit,
+            };
+            handle_periodic(&out, Period::Daily, &args)
+        }
+        Commands::Daily(args) => handle_periodic(&out, Period::Daily, &args),
+        Commands::Weekly(args) => handle_periodic(&out, Period::Weekly, &args),
+        Commands::Monthly(args) => handle_periodic(&out, Period::Monthly, &args),
+        Commands::Links { json, .. } => handle_links(&out, &title, json),
+        Commands::Backlinks { json, .. } => handle_backlinks(&out, &title, json),
+        Commands::Mv(args) => handle_mv(&out, &args),
+        Commands::Rm { yes, .. } => handle_rm(&out, &title, yes),
+        Commands::Trash(command) => handle_trash(&out, &title, command),
+        Commands::List(args) => handle_list(&out, &args),
+        Commands::Search(args) => handle_search(&out, &args),
+        Commands::Check { json } => handle_check(&out, json),
+        Commands::Tags { json } => handle_tags(&out, json),
+        Commands::Tasks(args) => handle_tasks(&out, &args),
+        Commands::Watch => handle_watch(&out),
+        Commands::Gui => {
             let exe =
                 env::var("CARGO_BIN_EXE_notes-gui").unwrap_or_else(|_| "notes-gui".to_string());
             if let Err(e) = Command::new(exe).status() {
-                eprintln!("Failed to launch GUI: {}", e);
-                process::exit(1);
+                out.error(&format!("Failed to launch GUI: {}", e));
+                process::exit(exit::UNAVAILABLE);
+            }
+            Ok(())
+        }
+        Commands::Vault => {
+            // with --quiet only the path, for use in scripts
+            if out.quiet {
+                println!("{}", location.path.display());
+            } else {
+                println!("{} (from {})", location.path.display(), location.source);
             }
+            Ok(())
+        }
+        Commands::Completions { shell } => {
+            print_completions(shell);
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        report_error(&out, &title, &location, &e);
+        process::exit(exit_code(&e));
+    }
+}
+
+/// The vault named on the command line or found from the current directory.
+fn locate(cli: &Cli) -> notes_core::Result<VaultLocation> {
+    let cwd = env::current_dir().map_err(|e| Error::io(Path::new("."), e))?;
+    find_vault(cli.vault.as_deref(), &cwd)
+}
+
+fn exit_code(err: &Error) -> i32 {
+    match err {
+        Error::NoteNotFound(_)
+        | Error::TemplateNotFound(_)
+        | Error::TaskNotFound(_)
+        | Error::AnchorNotFound { .. }
+        | Error::VaultNotFound(_) => exit::NO_INPUT,
+        Error::NoteExists(_) => exit::CANT_CREATE,
+        Error::InvalidTitle { .. } | Error::NotUtf8(_) | Error::DanglingAnchors(_) => {
+            exit::DATA_ERR
+        }
+        Error::PermissionDenied(_) => exit::NO_PERM,
+        Error::Config { .. } => exit::CONFIG,
+        Error::Editor { .. } => exit::UNAVAILABLE,
+        Error::Io { .. } | Error::Watch { .. } => exit::IO_ERR,
+    }
+}
+
+fn report_error(out: &Output, title: &str, location: &VaultLocation, err: &Error) {
+    let message = match err {
+        Error::NoteNotFound(_) => format!("Note '{}' not found.", title),
+        Error::InvalidTitle { reason, .. } => {
+            format!("'{}' is not a valid title: {}.", title, reason)
+        }
+        Error::NoteExists(path) => format!("{:?} already exists.", path),
+        Error::TemplateNotFound(name) => format!("Template '{}' not found.", name),
+        Error::TaskNotFound(id) => format!("Task '{}' not found.", id),
+        Error::AnchorNotFound { note, anchor } => {
+            format!("Note '{}' has no heading or block '{}'.", note, anchor)
         }
-        _ => {
-            println!("Error: Unknown command '{}'", command);
-            print_usage();
-            process::exit(1);
+        Error::DanglingAnchors(1) => "1 link points to a missing heading or block.".into(),
+        Error::DanglingAnchors(count) => {
+            format!("{} links point to missing headings or blocks.", count)
         }
+        Error::NotUtf8(path) => format!("{:?} is not a text note (not valid UTF-8).", path),
+        Error::PermissionDenied(path) => format!("Permission denied for {:?}.", path),
+        Error::VaultNotFound(path) => format!(
+            "Vault {:?} (from {}) does not exist.",
+            path, location.source
+        ),
+        Error::Io { .. } | Error::Config { .. } | Error::Editor { .. } | Error::Watch { .. } => {
+            err.to_string()
+        }
+    };
+    out.error(&message);
+}
+
+/// Open the vault for completing an argument. This runs before the command
+/// line is parsed, so `--vault` is looked for in the raw arguments.
+fn locate_for_completion() {
+    let flag = vault_arg(env::args_os().skip(1));
+    let location = env::current_dir()
+        .and_then(|cwd| find_vault(flag.as_deref(), &cwd).map_err(io::Error::other));
+    if let Ok(location) = location {
+        if let Ok((config, _)) = Config::for_vault(&location.path) {
+            set_config(config);
+        }
+        set_vault_dir(location.path);
     }
 }
 
-fn handle_new_note(title: &str) {
-    // For a new note, content is initially empty.
-    // Aliases are also empty for now.
-    let note = Note::new(title.to_string(), String::new(), None);
-    match note.save() {
-        Ok(_) => println!("Note '{}' created successfully at {:?}.", title, note.path),
-        Err(e) => eprintln!("Error creating note '{}': {}", title, e),
+/// The value of `--vault <DIR>` or `--vault=<DIR>` among `args`.
+fn vault_arg(mut args: impl Iterator<Item = OsString>) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        if arg == "--vault" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(value) = arg.to_str().and_then(|a| a.strip_prefix("--vault=")) {
+            return Some(value.into());
+        }
     }
+    None
 }
 
-fn handle_show_note(title: &str) {
-    let note_path = Note::path_from_title(title);
-    match Note::load(&note_path) {
-        Ok(note) => {
-            println!("--- {} ---", note.title);
-            println!("{}", note.content);
-            if !note.aliases.is_empty() {
-                println!("\nAliases: {:?}", note.aliases);
+/// Titles of all notes, offered when completing a title argument.
+fn note_titles() -> Vec<CompletionCandidate> {
+    locate_for_completion();
+    let Ok(data) = load_graph_data() else {
+        return Vec::new();
+    };
+    let mut titles: Vec<CompletionCandidate> = data
+        .graph
+        .nodes
+        .iter()
+        .map(|node| CompletionCandidate::new(&node.name))
+        .collect();
+    for node in &data.graph.nodes {
+        for alias in &node.aliases {
+            let help = format!("alias of {}", node.name);
+            titles.push(CompletionCandidate::new(alias).help(Some(help.into())));
+        }
+    }
+    titles
+}
+
+/// Names of the vault's templates, offered when completing `--template`.
+fn template_names() -> Vec<CompletionCandidate> {
+    locate_for_completion();
+    list_templates()
+        .unwrap_or_default()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Parse a `NAME=VALUE` template variable.
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err("expected NAME=VALUE".to_string()),
+    }
+}
+
+fn print_completions(shell: CompletionShell) {
+    let completer: &dyn EnvCompleter = match shell {
+        CompletionShell::Bash => &Bash,
+        CompletionShell::Zsh => &Zsh,
+        CompletionShell::Fish => &Fish,
+    };
+    let _ = completer.write_registration("COMPLETE", "notes", "notes", "notes", &mut io::stdout());
+}
+
+/// Create a note from a template, asking on the terminal for the values of
+/// template variables not given with `--var`.
+fn handle_new_note(
+    out: &Output,
+    title: &str,
+    template: Option<&str>,
+    vars: Vec<(String, String)>,
+) -> notes_core::Result<()> {
+    let template = match template {
+        Some(name) => Some(Template::load(name)?),
+        None => Template::default_template()?,
+    };
+    let mut values: HashMap<String, String> = vars.into_iter().collect();
+    if let Some(template) = &template
+        && io::stdin().is_terminal()
+    {
+        for name in template.variables() {
+            values
+                .entry(name)
+                .or_insert_with_key(|name| prompt(&format!("{}:", name)));
+        }
+    }
+    let note = create_note(title, template.as_ref(), &values)?;
+    out.info(&format!(
+        "Note '{}' created successfully at {:?}.",
+        title, note.path
+    ));
+    Ok(())
+}
+
+/// Open the note in the editor, creating it from the default template if
+/// there is none by that name, and bring the index up to date afterwards.
+fn handle_edit_note(out: &Output, title: &str) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    let path = match data.find(title) {
+        Some(i) => {
+            let node = &data.graph.nodes[i];
+            node.text_file().unwrap_or(&node.paths[0]).clone()
+        }
+        None => {
+            let note = create_from_template(title)?;
+            out.info(&format!(
+                "Created note '{}' at {:?}.",
+                note.title, note.path
+            ));
+            note.path
+        }
+    };
+    edit(&path)?;
+    // picks up the edit and rewrites the cached index
+    load_vault(out)?;
+    Ok(())
+}
+
+/// Open the periodic note `args` asks for in the editor, or print its path,
+/// creating it from the period's template first if needed.
+fn handle_periodic(out: &Output, period: Period, args: &PeriodArgs) -> notes_core::Result<()> {
+    let from = args.date.unwrap_or_else(|| Local::now().date_naive());
+    let date = period.shift(from, args.offset);
+    let (path, created) = open_periodic(period, date)?;
+    if created {
+        out.info(&format!(
+            "Created note '{}' at {:?}.",
+            period.title(date),
+            path
+        ));
+    }
+    if args.no_edit {
+        println!("{}", path.display());
+    } else {
+        edit(&path)?;
+    }
+    load_vault(out)?;
+    Ok(())
+}
+
+/// Show a note, with embeds expanded up to `render` levels deep if given.
+fn handle_show_note(out: &Output, title: &str, render: Option<usize>) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    // `Note#Heading` and `Note#^block` show a part of the note
+    if data.find(title).is_none()
+        && let Some((name, anchor)) = title.split_once('#')
+        && let Some(i) = data.find(name)
+    {
+        let (path, section) = resolve_anchor(&data, i, anchor)?;
+        let text = match render {
+            Some(max_depth) => render_note(out, &data, i, Some(anchor), max_depth, None)?,
+            None => {
+                let text = read_text(&path)?;
+                text[section.range].to_string()
             }
-            println!("\n(Source: {:?})", note.path);
+        };
+        println!("--- {} ---", title);
+        println!("{}", text.trim_end_matches('\n'));
+        println!(
+            "\n(Source: {:?}, lines {}-{})",
+            path, section.line, section.end_line
+        );
+        return Ok(());
+    }
+    let note_path = data
+        .find(title)
+        .and_then(|i| data.graph.nodes[i].text_file().cloned())
+        .unwrap_or_else(|| Note::path_from_title(title));
+    let note = Note::load(&note_path)?;
+    let content = match (render, data.find(title)) {
+        (Some(max_depth), Some(i)) => render_note(out, &data, i, None, max_depth, None)?,
+        _ => note.content.clone(),
+    };
+    println!("--- {} ---", note.title);
+    println!("{}", content);
+    if !note.aliases.is_empty() {
+        println!("\nAliases: {:?}", note.aliases);
+    }
+    println!("\n(Source: {:?})", note.path);
+    Ok(())
+}
+
+/// Load the vault's graph, warning about folders that could not be read.
+fn load_vault(out: &Output) -> notes_core::Result<GraphData> {
+    let data = load_graph_data()?;
+    for error in &data.skipped {
+        out.warning(&format!("{}; the notes in it are left out", error));
+    }
+    Ok(data)
+}
+
+/// Render a note or a part of it, warning about the embeds left as written.
+fn render_note(
+    out: &Output,
+    data: &GraphData,
+    i: usize,
+    anchor: Option<&str>,
+    max_depth: usize,
+    base: Option<PathBuf>,
+) -> notes_core::Result<String> {
+    let options = RenderOptions { max_depth, base };
+    let rendered = render(data, i, anchor, &options)?;
+    for problem in &rendered.problems {
+        out.warning(&format!("{}; left as written", problem));
+    }
+    Ok(rendered.text)
+}
+
+fn handle_export(
+    out: &Output,
+    title: &str,
+    output: Option<&Path>,
+    max_depth: usize,
+) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    let (i, anchor) = match data.find(title) {
+        Some(i) => (i, None),
+        None => title
+            .split_once('#')
+            .and_then(|(name, anchor)| Some((data.find(name)?, Some(anchor))))
+            .ok_or_else(|| Error::NoteNotFound(Note::path_from_title(title)))?,
+    };
+    // links to embedded files work from where the export is written
+    let base = match output {
+        Some(path) => path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf(),
+        None => env::current_dir().map_err(|e| Error::io(Path::new("."), e))?,
+    };
+    let text = render_note(out, &data, i, anchor, max_depth, Some(base.clone()))?;
+    match output {
+        Some(path) => {
+            fs::create_dir_all(&base).map_err(|e| Error::io(&base, e))?;
+            fs::write(path, &text).map_err(|e| Error::io(path, e))?;
+            out.info(&format!("Exported '{}' to {:?}.", title, path));
         }
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                eprintln!("Error: Note '{}' not found.", title);
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+fn handle_list(out: &Output, args: &ListArgs) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    let filter = Filter {
+        format: args.file_type.clone(),
+        folder: args.folder.clone(),
+        tag: args.tag.clone(),
+    };
+    let mut entries = list_notes(&data.graph, &filter, args.sort);
+    if args.reverse {
+        entries.reverse();
+    }
+    let format = if args.json {
+        OutputFormat::Json
+    } else {
+        args.format
+    };
+    match format {
+        OutputFormat::Human => print_human(&entries),
+        OutputFormat::Tsv => print_tsv(&entries),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&entries).expect("entries are always serializable")
+        ),
+    }
+    Ok(())
+}
+
+fn print_human(entries: &[Entry]) {
+    let width = entries
+        .iter()
+        .map(|e| e.path.display().to_string().chars().count())
+        .max()
+        .unwrap_or(0);
+    for entry in entries {
+        let links = match entry.links {
+            1 => "1 link".to_string(),
+            n => format!("{} links", n),
+        };
+        println!(
+            "{:<width$}  {:<12}  {}",
+            entry.path.display().to_string(),
+            entry.formats.join(","),
+            links,
+        );
+    }
+}
+
+/// One note per line with a header, tabs and newlines in values replaced by
+/// spaces and lists joined with commas.
+fn print_tsv(entries: &[Entry]) {
+    let clean = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+    println!("name\tpath\tformats\tmodified\tlinks\ttags\taliases");
+    for entry in entries {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            clean(&entry.name),
+            clean(&entry.path.display().to_string()),
+            entry.formats.join(","),
+            entry.modified.map(|m| m.to_string()).unwrap_or_default(),
+            entry.links,
+            clean(&entry.tags.join(",")),
+            clean(&entry.aliases.join(",")),
+        );
+    }
+}
+
+fn handle_search(out: &Output, args: &SearchArgs) -> notes_core::Result<()> {
+    // arguments the shell unquoted were phrases
+    let query: Vec<String> = args
+        .query
+        .iter()
+        .map(|word| {
+            if word.contains(char::is_whitespace) && !word.contains('"') {
+                format!("\"{}\"", word)
             } else {
-                eprintln!("Error loading note '{}': {}", title, e);
+                word.clone()
             }
+        })
+        .collect();
+    let data = load_vault(out)?;
+    let index = SearchIndex::build(&data)?;
+    let hits = index.search(&Query::parse(&query.join(" ")), args.limit);
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&hits).expect("hits are always serializable")
+        );
+    } else if hits.is_empty() {
+        out.info("No notes match.");
+    } else {
+        print_hits(out, &hits);
+    }
+    Ok(())
+}
+
+/// Each note's path followed by its matching lines.
+fn print_hits(out: &Output, hits: &[Hit]) {
+    for hit in hits {
+        println!("{}", hit.path.display());
+        for snippet in &hit.snippets {
+            print_snippet(out, snippet);
         }
     }
 }
 
-fn print_usage() {
-    println!("Usage: notes <command> [arguments]");
-    println!("Commands:");
-    println!("  new <title>      Create a new note with the given title.");
-    println!("  show <title>     Show the content of the note with the given title.");
-    println!("  gui              Launch graphical editor.");
-    // Future commands:
-    // println!("  edit <title>     Open the note with the given title for editing.");
-    // println!("  list             List all available notes.");
-    // println!("  link <from_title> <to_title> Create a link.");
-    // println!("  aliases <title> <alias1> [alias2...] Add aliases to a note.");
+/// An indented `file:line: text` line with the highlights colored.
+fn print_snippet(out: &Output, snippet: &Snippet) {
+    let mut text = String::new();
+    let mut last = 0;
+    for range in &snippet.highlights {
+        text.push_str(&snippet.text[last..range.start]);
+        let matched = &snippet.text[range.clone()];
+        if out.highlight {
+            text.push_str(&format!("\x1b[1;31m{}\x1b[0m", matched));
+        } else {
+            text.push_str(matched);
+        }
+        last = range.end;
+    }
+    text.push_str(&snippet.text[last..]);
+    println!("  {}:{}: {}", snippet.path.display(), snippet.line, text);
+}
+
+/// Print the links to missing headings and blocks, exiting with DATA_ERR if
+/// there are any.
+fn handle_check(out: &Output, json: bool) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    let dangling = dangling_anchors(&data)?;
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&dangling).expect("anchors are always serializable")
+        );
+    } else if dangling.is_empty() {
+        out.info("No dangling links to headings or blocks.");
+    } else {
+        for link in &dangling {
+            println!(
+                "{}:{}: '{}' has no heading or block '{}'",
+                link.path.display(),
+                link.line,
+                link.target,
+                link.anchor
+            );
+        }
+    }
+    if !dangling.is_empty() {
+        return Err(Error::DanglingAnchors(dangling.len()));
+    }
+    Ok(())
+}
+
+fn handle_tags(out: &Output, json: bool) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    let tree = tag_tree(&data.graph);
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&tree).expect("tags are always serializable")
+        );
+    } else if tree.is_empty() {
+        out.info("No notes have tags.");
+    } else {
+        print_tag_tree(&tree, 0);
+    }
+    Ok(())
+}
+
+/// Each tag by its last part, indented below the tag it is nested in.
+fn print_tag_tree(tree: &[TagCount], depth: usize) {
+    for tag in tree {
+        let name = tag.tag.rsplit('/').next().unwrap_or(&tag.tag);
+        let notes = match tag.notes {
+            1 => "1 note".to_string(),
+            n => format!("{} notes", n),
+        };
+        println!("{}#{}  {}", "  ".repeat(depth), name, notes);
+        print_tag_tree(&tag.children, depth + 1);
+    }
+}
+
+fn handle_tasks(out: &Output, args: &TasksArgs) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    if let Some(TasksCommand::Done { id }) = &args.command {
+        let task = complete_task(&data, id)?;
+        if task.status == Status::Done {
+            out.info(&format!("Task '{}' was already done.", task.text));
+        } else {
+            out.info(&format!("Checked '{}' in {:?}.", task.text, task.path));
+        }
+        // picks up the edit and rewrites the cached index
+        load_vault(out)?;
+        return Ok(());
+    }
+    let filter = TaskFilter {
+        status: args.status,
+        due_by: args.due,
+        tag: args.tag.clone(),
+    };
+    let tasks: Vec<Task> = collect_tasks(&data)?
+        .into_iter()
+        .filter(|task| filter.matches(task))
+        .collect();
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&tasks).expect("tasks are always serializable")
+        );
+    } else if tasks.is_empty() {
+        out.info("No tasks match.");
+    } else {
+        print_tasks(&tasks);
+    }
+    Ok(())
+}
+
+/// One task per line: id, checkbox, text, then due date, priority and
+/// where it is written.
+fn print_tasks(tasks: &[Task]) {
+    for task in tasks {
+        let mark = match task.status {
+            Status::Open => ' ',
+            Status::Done => 'x',
+        };
+        let mut details = Vec::new();
+        if let Some(due) = task.due {
+            details.push(format!("due {}", due));
+        }
+        if let Some(priority) = task.priority {
+            details.push(format!("{} priority", priority));
+        }
+        details.push(format!("{}:{}", task.path.display(), task.line));
+        println!(
+            "{}  [{}] {}  ({})",
+            task.id,
+            mark,
+            task.text,
+            details.join(", ")
+        );
+    }
+}
+
+fn handle_links(out: &Output, title: &str, json: bool) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    let i = data
+        .find(title)
+        .ok_or_else(|| Error::NoteNotFound(title.into()))?;
+    let links = outgoing(&data, i)?;
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&links).expect("links are always serializable")
+        );
+    } else if links.is_empty() {
+        out.info(&format!(
+            "'{}' links to no notes.",
+            data.graph.nodes[i].name
+        ));
+    } else {
+        print_references(out, &links);
+    }
+    Ok(())
+}
+
+fn handle_backlinks(out: &Output, title: &str, json: bool) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    let i = data
+        .find(title)
+        .ok_or_else(|| Error::NoteNotFound(title.into()))?;
+    let linked = backlinks(&data, i)?;
+    let unlinked = unlinked_mentions(&data, i)?;
+    if json {
+        let all = serde_json::json!({ "backlinks": linked, "unlinked": unlinked });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&all).expect("links are always serializable")
+        );
+        return Ok(());
+    }
+    if linked.is_empty() && unlinked.is_empty() {
+        out.info(&format!("No notes link to '{}'.", data.graph.nodes[i].name));
+    }
+    print_references(out, &linked);
+    if !unlinked.is_empty() {
+        if !linked.is_empty() {
+            println!();
+        }
+        println!("Unlinked mentions:");
+        print_references(out, &unlinked);
+    }
+    Ok(())
+}
+
+/// Show how renaming a note changes the vault, then rename it once
+/// confirmed.
+fn handle_mv(out: &Output, args: &MvArgs) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    let i = data
+        .find(&args.old)
+        .ok_or_else(|| Error::NoteNotFound(args.old.clone().into()))?;
+    let options = RenameOptions {
+        mentions: args.mentions,
+    };
+    let plan = plan_rename(&data, i, &args.new, options)?;
+    if !args.dry_run && !args.yes {
+        require_terminal(out, "rename");
+    }
+    if !out.quiet || args.dry_run {
+        print_diff(out, &plan.diff());
+    }
+    let mentions = data
+        .graph
+        .incoming(i)
+        .filter(|e| e.kind == LinkKind::Implicit && e.alias.is_none())
+        .count();
+    if !args.mentions && mentions > 0 {
+        out.warning(&format!(
+            "{} notes mention '{}' without a link; --mentions rewrites them too.",
+            mentions, data.graph.nodes[i].name
+        ));
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+    if !args.yes && !confirm("Rename?") {
+        out.info("Nothing renamed.");
+        return Ok(());
+    }
+    apply(&plan)?;
+    // brings the cached index up to date
+    load_vault(out)?;
+    out.info(&format!(
+        "Renamed '{}' to '{}', {} files updated.",
+        plan.old_path.display(),
+        plan.new_path.display(),
+        plan.edits.len()
+    ));
+    Ok(())
+}
+
+/// Move a note to the trash, asking first if that breaks links to it.
+fn handle_rm(out: &Output, title: &str, yes: bool) -> notes_core::Result<()> {
+    let data = load_vault(out)?;
+    let i = data
+        .find(title)
+        .ok_or_else(|| Error::NoteNotFound(title.into()))?;
+    let name = &data.graph.nodes[i].name;
+    let linked = backlinks(&data, i)?;
+    if !linked.is_empty() {
+        let paths: Vec<String> = linked
+            .iter()
+            .map(|r| r.path.display().to_string())
+            .collect();
+        out.warning(&format!(
+            "Notes linking to '{}' will lose the link: {}",
+            name,
+            paths.join(", ")
+        ));
+    }
+    let breaks = linked.iter().any(|r| r.kind == ReferenceKind::Explicit);
+    if breaks && !yes {
+        require_terminal(out, "delete");
+        if !confirm("Move to the trash anyway?") {
+            out.info("Nothing deleted.");
+            return Ok(());
+        }
+    }
+    let entry = trash_node(&data, i)?;
+    // brings the cached index up to date
+    load_vault(out)?;
+    out.info(&format!(
+        "Moved '{}' to the trash; restore it with `notes trash restore {}`.",
+        entry.rel_path.display(),
+        entry.id
+    ));
+    Ok(())
+}
+
+fn handle_trash(out: &Output, key: &str, command: TrashCommand) -> notes_core::Result<()> {
+    let root = vault_dir();
+    match command {
+        TrashCommand::List { json } => {
+            let entries = list_trash(&root)?;
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&entries)
+                        .expect("entries are always serializable")
+                );
+            } else if entries.is_empty() {
+                out.info("The trash is empty.");
+            } else {
+                print_trash(&entries);
+            }
+        }
+        TrashCommand::Restore { .. } => {
+            let entry = find_entry(&root, key)?.ok_or_else(|| Error::NoteNotFound(key.into()))?;
+            restore(&root, &entry)?;
+            load_vault(out)?;
+            out.info(&format!("Restored '{}'.", entry.rel_path.display()));
+        }
+        TrashCommand::Empty { yes } => {
+            if !yes {
+                require_terminal(out, "empty the trash");
+                if !confirm("Delete the notes in the trash for good?") {
+                    out.info("Nothing deleted.");
+                    return Ok(());
+                }
+            }
+            match empty_trash(&root)? {
+                0 => out.info("The trash is empty."),
+                1 => out.info("Deleted 1 note for good."),
+                n => out.info(&format!("Deleted {} notes for good.", n)),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_trash(entries: &[TrashEntry]) {
+    let width = entries.iter().map(|e| e.id.len()).max().unwrap_or(0);
+    for entry in entries {
+        println!(
+            "{:<width$}  {}  {}  ({} files)",
+            entry.id,
+            entry.deleted.format("%Y-%m-%d %H:%M"),
+            entry.rel_path.display(),
+            entry.files.len(),
+            width = width
+        );
+    }
+}
+
+/// Exit unless confirmation can be asked for on a terminal.
+fn require_terminal(out: &Output, action: &str) {
+    if !io::stdin().is_terminal() {
+        out.error(&format!(
+            "Refusing to {} without confirmation; pass --yes to {} anyway.",
+            action, action
+        ));
+        process::exit(exit::USAGE);
+    }
+}
+
+/// Print a diff, removed lines in red and added ones in green.
+fn print_diff(out: &Output, diff: &str) {
+    for line in diff.lines() {
+        let color = match line.as_bytes().first() {
+            _ if !out.highlight => None,
+            _ if line.starts_with("---") || line.starts_with("+++") => Some("1"),
+            Some(b'-') => Some("31"),
+            Some(b'+') => Some("32"),
+            Some(b'@') => Some("36"),
+            _ => None,
+        };
+        match color {
+            Some(color) => println!("\x1b[{}m{}\x1b[0m", color, line),
+            None => println!("{}", line),
+        }
+    }
+}
+
+/// Ask a yes/no question on the terminal; anything but yes means no.
+fn confirm(question: &str) -> bool {
+    let answer = prompt(&format!("{} [y/N]", question));
+    matches!(answer.to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Ask for a line of input on the terminal, returned without surrounding
+/// whitespace.
+fn prompt(question: &str) -> String {
+    print!("{} ", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    let _ = io::stdin().lock().read_line(&mut answer);
+    answer.trim().to_string()
+}
+
+/// Each note's path and how it refers, followed by the lines that do.
+fn print_references(out: &Output, references: &[Reference]) {
+    for reference in references {
+        let kind = match reference.kind {
+            ReferenceKind::Explicit => "explicit",
+            ReferenceKind::Implicit => "implicit",
+            ReferenceKind::Unlinked => "unlinked",
+        };
+        match &reference.alias {
+            Some(alias) => println!("{}  {} via '{}'", reference.path.display(), kind, alias),
+            None => println!("{}  {}", reference.path.display(), kind),
+        }
+        for snippet in &reference.context {
+            print_snippet(out, snippet);
+        }
+    }
+}
+
+/// Keep the graph up to date while notes change and print what changed.
+/// Runs until interrupted.
+fn handle_watch(out: &Output) -> notes_core::Result<()> {
+    let mut data = load_vault(out)?;
+    let watcher = VaultWatcher::new(&vault_dir())?;
+    out.info(&format!(
+        "Watching {:?} ({} notes). Press Ctrl-C to stop.",
+        vault_dir(),
+        data.graph.nodes.len()
+    ));
+    for paths in watcher.subscribe() {
+        let changes = match update_paths(&mut data, &paths) {
+            Ok(changes) => changes,
+            Err(e) => {
+                out.error(&e.to_string());
+                continue;
+            }
+        };
+        for path in &changes.added {
+            println!("added     {}", path.display());
+        }
+        for path in &changes.modified {
+            println!("modified  {}", path.display());
+        }
+        for path in &changes.removed {
+            println!("removed   {}", path.display());
+        }
+        if !changes.is_empty() {
+            let _ = save_index(&data);
+        }
+    }
+    Ok(())
 }
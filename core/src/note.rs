@@ -1,8 +1,11 @@
 use once_cell::sync::OnceCell;
 use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
-// Intentionally removed: use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::config::config;
+use crate::error::{Error, Result};
+use crate::frontmatter::{Frontmatter, Value};
 
 pub const NOTES_DIR: &str = "notes";
 
@@ -19,57 +22,226 @@ pub fn vault_dir() -> PathBuf {
         .unwrap_or_else(|| PathBuf::from(NOTES_DIR))
 }
 
+/// Read a text file. Contents that are not valid UTF-8 are reported as
+/// [`Error::NotUtf8`].
+pub fn read_text(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+    String::from_utf8(bytes)
+        .map_err(|e| Error::io(path, io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+/// Characters that are not allowed in file names on at least one platform.
+const UNSAFE_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Names Windows reserves for devices, with or without an extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Longest file stem we create, in bytes, leaving room for an extension.
+const MAX_STEM_LEN: usize = 200;
+
+/// Tidy up a title typed by the user: surrounding whitespace is dropped and
+/// so is a trailing `.md`, since the extension is added on save anyway.
+pub fn normalize_title(title: &str) -> String {
+    let title = title.trim();
+    let lower = title.to_ascii_lowercase();
+    match lower.strip_suffix(".md") {
+        Some(rest) if !rest.trim().is_empty() => title[..rest.len()].trim_end().to_string(),
+        _ => title.to_string(),
+    }
+}
+
+/// Map a title to the file stem the note is stored under.
+///
+/// Path separators, characters Windows forbids and control characters become
+/// `-`. Leading dots (hidden files, `..`) and trailing dots and spaces are
+/// dropped, reserved device names get a `_` appended (`nul.txt` becomes
+/// `nul_.txt`) and overly long titles are shortened. When the stem differs
+/// from the title, the title is kept in the note's frontmatter so it survives
+/// a load. Titles that are empty or consist only of characters that get
+/// removed are rejected.
+pub fn file_stem_for_title(title: &str) -> Result<String> {
+    let title = normalize_title(title);
+    if title.is_empty() {
+        return Err(Error::invalid_title(&title, "title is empty"));
+    }
+    let mapped: String = title
+        .chars()
+        .map(|c| {
+            if UNSAFE_CHARS.contains(&c) || c.is_control() {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let mut stem = mapped
+        .trim_start_matches(['.', ' '])
+        .trim_end_matches(['.', ' '])
+        .to_string();
+    if stem.is_empty() {
+        return Err(Error::invalid_title(
+            &title,
+            "title has no characters usable in a file name",
+        ));
+    }
+    // Windows ignores the extension, so the device part itself is changed
+    let device = stem.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(device))
+    {
+        stem.insert(device.len(), '_');
+    }
+    if stem.len() > MAX_STEM_LEN {
+        let mut end = MAX_STEM_LEN;
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        stem.truncate(end);
+        stem = stem.trim_end_matches(['.', ' ']).to_string();
+    }
+    Ok(stem)
+}
+
 #[derive(Debug, Clone)]
 pub struct Note {
     pub title: String,
+    /// Body of the note, without the frontmatter block
     pub content: String,
     pub aliases: Vec<String>,
+    pub tags: Vec<String>,
+    /// Frontmatter as read from disk, `None` if the file had none
+    pub frontmatter: Option<Frontmatter>,
     pub path: PathBuf,
 }
 
 impl Note {
+    /// Create a note in the vault root. The title is cleaned up with
+    /// [`normalize_title`]; an unusable title is reported by [`Note::save`].
     pub fn new(title: String, content: String, aliases: Option<Vec<String>>) -> Self {
-        let mut path = vault_dir();
-        path.push(format!("{}.md", title));
+        let title = normalize_title(&title);
+        let path = Note::path_from_title(&title);
         Note {
             title,
             content,
-            aliases: aliases.unwrap_or_else(Vec::new),
+            aliases: aliases.unwrap_or_default(),
+            tags: Vec::new(),
+            frontmatter: None,
             path,
         }
     }
 
-    pub fn save(&self) -> io::Result<()> {
-        fs::create_dir_all(vault_dir())?;
-        let mut file = fs::File::create(&self.path)?;
-        file.write_all(self.content.as_bytes())?;
-        Ok(())
+    /// Date the note was created, as written in its frontmatter.
+    pub fn created(&self) -> Option<String> {
+        self.frontmatter.as_ref()?.get_str("created")
+    }
+
+    /// Date the note was last modified, as written in its frontmatter.
+    pub fn modified(&self) -> Option<String> {
+        self.frontmatter.as_ref()?.get_str("modified")
+    }
+
+    /// Full file contents: the frontmatter block (if any) followed by the body.
+    pub fn to_text(&self) -> String {
+        let mut frontmatter = self.frontmatter.clone();
+        let title_differs = self.path.file_stem().and_then(|s| s.to_str()) != Some(&self.title);
+        if frontmatter.is_none()
+            && (title_differs || !self.aliases.is_empty() || !self.tags.is_empty())
+        {
+            frontmatter = Some(Frontmatter::default());
+        }
+        let mut out = String::new();
+        if let Some(mut fm) = frontmatter {
+            if title_differs || fm.get("title").is_some() {
+                fm.set("title", Value::String(self.title.clone()));
+            }
+            fm.set_list("aliases", &self.aliases);
+            fm.set_list("tags", &self.tags);
+            out.push_str(&fm.render());
+        }
+        out.push_str(&self.content);
+        out
+    }
+
+    /// Write the note to its path. A file there that holds a note with a
+    /// different title, which maps to the same file name, is left alone and
+    /// reported as [`Error::NoteExists`].
+    pub fn save(&self) -> Result<()> {
+        if self.path.is_file() {
+            let existing = Note::load(&self.path)?;
+            if normalize_title(&existing.title) != self.title {
+                return Err(Error::NoteExists(self.path.clone()));
+            }
+        }
+        self.write(false)
+    }
+
+    /// Save a new note, failing with [`Error::NoteExists`] instead of
+    /// overwriting a file already at its path.
+    pub fn create(&self) -> Result<()> {
+        self.write(true)
+    }
+
+    fn write(&self, create_new: bool) -> Result<()> {
+        file_stem_for_title(&self.title)?;
+        let dir = self
+            .path
+            .parent()
+            .map(PathBuf::from)
+            .unwrap_or_else(vault_dir);
+        fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(!create_new)
+            .create_new(create_new)
+            .open(&self.path)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::AlreadyExists => Error::NoteExists(self.path.clone()),
+                _ => Error::io(&self.path, e),
+            })?;
+        file.write_all(self.to_text().as_bytes())
+            .map_err(|e| Error::io(&self.path, e))
     }
 
-    pub fn load(path: &PathBuf) -> io::Result<Self> {
-        if !path.exists() {
-            return Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                "Note file not found",
-            ));
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            return Err(Error::NoteNotFound(path.to_path_buf()));
         }
-        let content = fs::read_to_string(path)?;
-        let title = path
-            .file_stem()
-            .and_then(|stem| stem.to_str())
-            .map(String::from)
+        let text = read_text(path)?;
+        let (frontmatter, body) = Frontmatter::split(&text);
+        let title = frontmatter
+            .as_ref()
+            .and_then(|fm| fm.get_str("title"))
+            .or_else(|| path.file_stem().and_then(|s| s.to_str()).map(String::from))
             .unwrap_or_else(|| "Untitled".to_string());
         Ok(Note {
             title,
-            content,
-            aliases: Vec::new(),
-            path: path.clone(),
+            content: body.to_string(),
+            aliases: frontmatter
+                .as_ref()
+                .map(|fm| fm.get_list("aliases"))
+                .unwrap_or_default(),
+            tags: frontmatter
+                .as_ref()
+                .map(|fm| fm.get_list("tags"))
+                .unwrap_or_default(),
+            frontmatter,
+            path: path.to_path_buf(),
         })
     }
 
+    /// Path of the note with the given title in the vault root, in the
+    /// configured default format. Titles that cannot be mapped to a file name
+    /// are used as they are; saving such a note fails with
+    /// [`Error::InvalidTitle`].
     pub fn path_from_title(title: &str) -> PathBuf {
+        let stem = file_stem_for_title(title).unwrap_or_else(|_| normalize_title(title));
         let mut path = vault_dir();
-        path.push(format!("{}.md", title));
+        path.push(format!("{}.{}", stem, config().default_format));
         path
     }
 }
@@ -0,0 +1,246 @@
+//! Daily, weekly and monthly notes, named after the period they cover.
+//!
+//! Periodic notes live in the configured folder, `journal/` unless set
+//! otherwise, and are named by formatting the first day of their period with
+//! the configured strftime format, e.g. `2026-10-18`, `2026-W42` or
+//! `2026-10`. A new one is filled in from the template named after its
+//! period (`daily`, `weekly`, `monthly`), else from the default template.
+//!
+//! A daily note is also linked by the date written out, as in
+//! `October 18, 2026` (see [`date_aliases`]).
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, Days, Local, Months, NaiveDate, TimeZone};
+
+use crate::config::config;
+use crate::editor::{Template, note_from_text};
+use crate::error::{Error, Result};
+use crate::note::{file_stem_for_title, vault_dir};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Period::Daily => "daily",
+            Period::Weekly => "weekly",
+            Period::Monthly => "monthly",
+        })
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "daily" => Ok(Period::Daily),
+            "weekly" => Ok(Period::Weekly),
+            "monthly" => Ok(Period::Monthly),
+            other => Err(format!(
+                "unknown period '{}', expected daily, weekly or monthly",
+                other
+            )),
+        }
+    }
+}
+
+impl Period {
+    /// First day of the period `date` falls in. Weeks start on Monday.
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Daily => date,
+            Period::Weekly => {
+                let since_monday = date.weekday().num_days_from_monday();
+                date - Days::new(since_monday.into())
+            }
+            Period::Monthly => date.with_day(1).expect("every month has a first day"),
+        }
+    }
+
+    /// First day of the period `offset` periods before or after the one
+    /// `date` falls in.
+    pub fn shift(self, date: NaiveDate, offset: i32) -> NaiveDate {
+        let start = self.start(date);
+        let n = offset.unsigned_abs();
+        let shifted = match self {
+            Period::Daily | Period::Weekly => {
+                let days = Days::new(if self == Period::Daily { n } else { n * 7 }.into());
+                if offset < 0 {
+                    start.checked_sub_days(days)
+                } else {
+                    start.checked_add_days(days)
+                }
+            }
+            Period::Monthly => {
+                if offset < 0 {
+                    start.checked_sub_months(Months::new(n))
+                } else {
+                    start.checked_add_months(Months::new(n))
+                }
+            }
+        };
+        shifted.unwrap_or(start)
+    }
+
+    fn format(self) -> &'static str {
+        let periodic = &config().periodic;
+        match self {
+            Period::Daily => &periodic.daily,
+            Period::Weekly => &periodic.weekly,
+            Period::Monthly => &periodic.monthly,
+        }
+    }
+
+    /// Title of the note for the period `date` falls in.
+    pub fn title(self, date: NaiveDate) -> String {
+        self.start(date).format(self.format()).to_string()
+    }
+}
+
+/// Whether `format` is a strftime format chrono understands.
+pub(crate) fn valid_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| item == Item::Error)
+}
+
+/// Folder of periodic notes inside the vault.
+pub fn periodic_dir() -> PathBuf {
+    vault_dir().join(&config().periodic.folder)
+}
+
+/// The file of the note for the period `date` falls in, preferring the
+/// default format if there are several. `None` if there is no such note.
+pub fn find_periodic(period: Period, date: NaiveDate) -> Result<Option<PathBuf>> {
+    let stem = file_stem_for_title(&period.title(date))?;
+    let dir = periodic_dir();
+    let preferred = dir.join(format!("{}.{}", stem, config().default_format));
+    if preferred.is_file() {
+        return Ok(Some(preferred));
+    }
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::io(&dir, e)),
+    };
+    let mut found: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.file_stem().and_then(|s| s.to_str()) == Some(&stem))
+        .collect();
+    found.sort();
+    Ok(found.into_iter().next())
+}
+
+/// The note for the period `date` falls in, created from the period's
+/// template if it does not exist yet. Returns its path and whether it was
+/// created.
+pub fn open_periodic(period: Period, date: NaiveDate) -> Result<(PathBuf, bool)> {
+    if let Some(path) = find_periodic(period, date)? {
+        return Ok((path, false));
+    }
+    let title = period.title(date);
+    let template = match Template::load(&period.to_string()) {
+        Ok(template) => Some(template),
+        Err(Error::TemplateNotFound(_)) => Template::default_template()?,
+        Err(e) => return Err(e),
+    };
+    // placeholders like {{date}} refer to the period, not to today
+    let start = period.start(date);
+    let now = Local::now();
+    let at = Local
+        .from_local_datetime(&start.and_time(now.time()))
+        .earliest()
+        .unwrap_or(now);
+    let text = template
+        .map(|t| t.render_at(&title, &HashMap::new(), at))
+        .unwrap_or_default();
+    let mut note = note_from_text(&title, &text);
+    let file_name = note.path.file_name().expect("notes have a file name");
+    note.path = periodic_dir().join(file_name);
+    note.save()?;
+    Ok((note.path, true))
+}
+
+/// Other ways of writing the date of the daily note `name` in `folder`,
+/// which link to it like aliases. Empty for any other note.
+pub(crate) fn date_aliases(name: &str, folder: &Path) -> Vec<String> {
+    let periodic = &config().periodic;
+    if folder != periodic.folder {
+        return Vec::new();
+    }
+    match NaiveDate::parse_from_str(name, &periodic.daily) {
+        Ok(date) => ["%B %-d, %Y", "%-d %B %Y"]
+            .iter()
+            .map(|format| date.format(format).to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Period, date_aliases, valid_format};
+    use crate::graph::load_from;
+    use crate::testing::TempDir;
+    use chrono::NaiveDate;
+    use std::fs;
+    use std::path::Path;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn periods() {
+        let sunday = date("2026-10-18");
+        assert_eq!(Period::Daily.title(sunday), "2026-10-18");
+        assert_eq!(Period::Weekly.title(sunday), "2026-W42");
+        assert_eq!(Period::Monthly.title(sunday), "2026-10");
+        assert_eq!(Period::Weekly.start(sunday), date("2026-10-12"));
+        assert_eq!(Period::Daily.shift(sunday, -1), date("2026-10-17"));
+        assert_eq!(Period::Weekly.shift(sunday, 1), date("2026-10-19"));
+        assert_eq!(
+            Period::Monthly.shift(date("2026-01-31"), -2),
+            date("2025-11-01")
+        );
+        assert_eq!("weekly".parse::<Period>(), Ok(Period::Weekly));
+        assert!("yearly".parse::<Period>().is_err());
+        assert!(valid_format("%G-W%V"));
+        assert!(!valid_format("%Q"));
+    }
+
+    #[test]
+    fn daily_notes_are_linked_by_written_out_dates() {
+        assert_eq!(
+            date_aliases("2026-10-08", Path::new("journal")),
+            ["October 8, 2026", "8 October 2026"]
+        );
+        assert!(date_aliases("2026-10-08", Path::new("")).is_empty());
+        assert!(date_aliases("2026-W42", Path::new("journal")).is_empty());
+
+        let root = TempDir::new("periodic");
+        fs::create_dir_all(root.join("journal")).unwrap();
+        fs::write(root.join("journal/2026-10-08.md"), "Rain.\n").unwrap();
+        fs::write(root.join("Trip.md"), "Left on 8 October 2026.\n").unwrap();
+        fs::write(root.join("Plan.md"), "Due 2026-10-08.\n").unwrap();
+
+        let data = load_from(&root).unwrap();
+        let day = data.find("2026-10-08").unwrap();
+        let edges = data.graph.outgoing(data.find("Trip").unwrap());
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].to, day);
+        assert_eq!(edges[0].alias.as_deref(), Some("8 October 2026"));
+        let edges = data.graph.outgoing(data.find("Plan").unwrap());
+        assert_eq!(edges[0].to, day);
+    }
+}
@@ -0,0 +1,258 @@
+//! Links between notes together with the lines they occur on, as used by
+//! `notes links` and `notes backlinks`.
+//!
+//! Besides the links of the graph, a note can be mentioned without being
+//! linked: its name occurs only inside a longer name, which takes the link,
+//! or implicit links are turned off. Such unlinked mentions are found by
+//! [`unlinked_mentions`].
+
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::frontmatter::Frontmatter;
+use crate::graph::{Edge, GraphData, LinkKind, normalize, resolve_target, unlinked_targets};
+use crate::links::parse_links;
+use crate::search::{Snippet, shorten};
+
+/// How one note refers to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceKind {
+    /// A `[[wikilink]]` or Markdown link
+    Explicit,
+    /// A mention of the name or an alias that counts as a link
+    Implicit,
+    /// A mention that does not count as a link
+    Unlinked,
+}
+
+impl From<LinkKind> for ReferenceKind {
+    fn from(kind: LinkKind) -> Self {
+        match kind {
+            LinkKind::Explicit => ReferenceKind::Explicit,
+            LinkKind::Implicit => ReferenceKind::Implicit,
+        }
+    }
+}
+
+/// A reference between the note asked about and another note.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reference {
+    /// Name of the note at the other end
+    pub name: String,
+    /// Path of the note at the other end, relative to the vault without
+    /// extension
+    pub path: PathBuf,
+    pub kind: ReferenceKind,
+    /// Alias of the target that was mentioned, if not its name
+    pub alias: Option<String>,
+    /// Lines of the referring note that link to or mention the target
+    pub context: Vec<Snippet>,
+}
+
+/// Notes that node `i` links to, sorted by path.
+pub fn outgoing(data: &GraphData, i: usize) -> Result<Vec<Reference>> {
+    let edges = data.graph.outgoing(i);
+    references(data, edges.iter(), |edge| edge.to)
+}
+
+/// Notes that link to node `i`, sorted by path.
+pub fn backlinks(data: &GraphData, i: usize) -> Result<Vec<Reference>> {
+    references(data, data.graph.incoming(i), |edge| edge.from)
+}
+
+/// Notes that mention node `i` without linking to it, sorted by path.
+pub fn unlinked_mentions(data: &GraphData, i: usize) -> Result<Vec<Reference>> {
+    let mut out = Vec::new();
+    for from in 0..data.graph.nodes.len() {
+        if from == i || unlinked_targets(data, from).binary_search(&i).is_err() {
+            continue;
+        }
+        let node = &data.graph.nodes[from];
+        out.push(Reference {
+            name: node.name.clone(),
+            path: node.rel_path.clone(),
+            kind: ReferenceKind::Unlinked,
+            alias: None,
+            context: context(data, from, i)?,
+        });
+    }
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(out)
+}
+
+fn references<'a>(
+    data: &GraphData,
+    edges: impl Iterator<Item = &'a Edge>,
+    other: impl Fn(&Edge) -> usize,
+) -> Result<Vec<Reference>> {
+    let mut out = Vec::new();
+    for edge in edges {
+        let node = &data.graph.nodes[other(edge)];
+        out.push(Reference {
+            name: node.name.clone(),
+            path: node.rel_path.clone(),
+            kind: edge.kind.into(),
+            alias: edge.alias.clone(),
+            context: context(data, edge.from, edge.to)?,
+        });
+    }
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(out)
+}
+
+/// Lines of node `from` with an explicit link to node `to` or a mention of
+/// its name or aliases, the links and mentions highlighted.
+fn context(data: &GraphData, from: usize, to: usize) -> Result<Vec<Snippet>> {
+    let source = &data.graph.nodes[from];
+    let target = &data.graph.nodes[to];
+    let names: Vec<String> = std::iter::once(&target.name)
+        .chain(&target.aliases)
+        .map(|name| normalize(name))
+        .filter(|name| !name.is_empty())
+        .collect();
+    let mut out = Vec::new();
+    for path in &source.paths {
+        let bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+        let Ok(text) = String::from_utf8(bytes) else {
+            continue;
+        };
+        let (_, body) = Frontmatter::split(&text);
+        let skipped_lines = text[..text.len() - body.len()].matches('\n').count();
+        let links: Vec<Range<usize>> = parse_links(body)
+            .into_iter()
+            .filter(|link| {
+                resolve_target(data, &link.target, source.folder()).is_some_and(|(j, _)| j == to)
+            })
+            .map(|link| link.span)
+            .collect();
+        let rel_path = path.strip_prefix(data.root()).unwrap_or(path);
+
+        let mut line_start = 0;
+        for (n, line) in body.split_inclusive('\n').enumerate() {
+            let line_end = line_start + line.len();
+            let mut highlights: Vec<Range<usize>> = links
+                .iter()
+                .filter(|span| span.start >= line_start && span.end <= line_end)
+                .map(|span| span.start - line_start..span.end - line_start)
+                .collect();
+            highlights.extend(find_names(line, &names));
+            line_start = line_end;
+            if highlights.is_empty() {
+                continue;
+            }
+            let (text, highlights) = shorten(line, highlights);
+            out.push(Snippet {
+                path: rel_path.to_path_buf(),
+                line: skipped_lines + n + 1,
+                text,
+                highlights,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Byte ranges of `line` where one of the normalized `names` occurs on word
+/// boundaries, compared the way implicit links are.
+pub(crate) fn find_names(line: &str, names: &[String]) -> Vec<Range<usize>> {
+    // the normalized line, with the byte range in `line` of the character
+    // each of its bytes came from
+    let mut normalized = String::new();
+    let mut origin: Vec<Range<usize>> = Vec::new();
+    let mut in_space = false;
+    for (i, c) in line.char_indices() {
+        if !c.is_alphanumeric() {
+            in_space = true;
+            continue;
+        }
+        if in_space && !normalized.is_empty() {
+            normalized.push(' ');
+            origin.push(i..i);
+        }
+        in_space = false;
+        for lower in c.to_lowercase() {
+            normalized.push(lower);
+            origin.resize(normalized.len(), i..i + c.len_utf8());
+        }
+    }
+    let bytes = normalized.as_bytes();
+    let mut out = Vec::new();
+    for name in names {
+        for (start, _) in normalized.match_indices(name.as_str()) {
+            let end = start + name.len();
+            if (start == 0 || bytes[start - 1] == b' ')
+                && (end == bytes.len() || bytes[end] == b' ')
+            {
+                out.push(origin[start].start..origin[end - 1].end);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReferenceKind, backlinks, find_names, outgoing, unlinked_mentions};
+    use crate::graph::load_from;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn finds_names_in_raw_text() {
+        let line = "About Nuclear-Power, and nuclear power plants.";
+        let names = vec!["nuclear power".to_string(), "power".to_string()];
+        let found: Vec<&str> = find_names(line, &names)
+            .into_iter()
+            .map(|r| &line[r])
+            .collect();
+        assert_eq!(found, ["Nuclear-Power", "nuclear power", "Power", "power"]);
+        assert!(find_names("powerful", &names).is_empty());
+    }
+
+    #[test]
+    fn links_backlinks_and_unlinked_mentions() {
+        let root = TempDir::new("references");
+        fs::write(root.join("Power.md"), "Just power.\n").unwrap();
+        fs::write(root.join("Nuclear Power.md"), "A kind of [[Power]].\n").unwrap();
+        fs::write(
+            root.join("Plan.md"),
+            "---\ntags: [x]\n---\nBuild nuclear power.\n\nThen rest.\n",
+        )
+        .unwrap();
+
+        let data = load_from(&root).unwrap();
+        let plan = data.find("Plan").unwrap();
+        let power = data.find("Power").unwrap();
+        let nuclear = data.find("Nuclear Power").unwrap();
+
+        let links = outgoing(&data, plan).unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].name, "Nuclear Power");
+        assert_eq!(links[0].kind, ReferenceKind::Implicit);
+        assert_eq!(links[0].context[0].line, 4);
+        assert_eq!(links[0].context[0].text, "Build nuclear power.");
+
+        let back = backlinks(&data, power).unwrap();
+        let names: Vec<_> = back.iter().map(|r| (r.name.as_str(), r.kind)).collect();
+        assert_eq!(names, [("Nuclear Power", ReferenceKind::Explicit)]);
+        let snippet = &back[0].context[0];
+        let highlighted: Vec<_> = snippet
+            .highlights
+            .iter()
+            .map(|r| &snippet.text[r.clone()])
+            .collect();
+        assert_eq!(highlighted, ["[[Power]]"]);
+        assert_eq!(backlinks(&data, nuclear).unwrap()[0].name, "Plan");
+
+        let unlinked = unlinked_mentions(&data, power).unwrap();
+        assert_eq!(unlinked.len(), 1);
+        assert_eq!(unlinked[0].name, "Plan");
+        assert_eq!(unlinked[0].kind, ReferenceKind::Unlinked);
+        assert!(unlinked_mentions(&data, nuclear).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,628 @@
+//! Renaming a note together with the links that point at it, as used by
+//! `notes mv`.
+//!
+//! A rename is planned first with [`plan_rename`]: every file of the note
+//! gets the new name and every note linking to it gets its links rewritten.
+//! The plan can be shown with [`RenamePlan::diff`] before [`apply`] carries
+//! it out.
+//!
+//! [`apply`] writes the new contents of all edited notes and a journal of
+//! every step to `.notes/rename/` before it touches the vault. Should it be
+//! interrupted, [`recover`] finishes the rename from the journal before the
+//! next command that changes the vault, so a note's files never stay split
+//! between the old and the new name. Commands that only read the vault leave
+//! it alone.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::frontmatter::{Frontmatter, Value};
+use crate::graph::{GraphData, LinkKind, canonicalize, normalize, resolve_target};
+use crate::links::{parse_links, percent_decode};
+use crate::note::{file_stem_for_title, normalize_title};
+use crate::references::find_names;
+use crate::vault::STATE_DIR;
+
+/// Folder inside [`STATE_DIR`] where a rename in progress is staged.
+const RENAME_DIR: &str = "rename";
+const JOURNAL: &str = "journal.json";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenameOptions {
+    /// Also replace plain-text mentions of the old name in notes that link
+    /// to the renamed one
+    pub mentions: bool,
+}
+
+/// New contents of a text file touched by a rename.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileEdit {
+    /// Path of the file once the rename is done
+    pub path: PathBuf,
+    pub old: String,
+    pub new: String,
+}
+
+/// Everything a rename changes, worked out before anything is written.
+#[derive(Debug, Clone)]
+pub struct RenamePlan {
+    pub root: PathBuf,
+    pub old_path: PathBuf,
+    /// Path of the note relative to the vault without extension once renamed
+    pub new_path: PathBuf,
+    /// Files of the note, each with the path it moves to
+    pub moves: Vec<(PathBuf, PathBuf)>,
+    /// Text files whose contents change, sorted by path
+    pub edits: Vec<FileEdit>,
+}
+
+/// Steps of a rename in progress, with paths relative to the vault.
+#[derive(Debug, Serialize, Deserialize)]
+struct Journal {
+    moves: Vec<(PathBuf, PathBuf)>,
+    /// Staged contents and the file they replace
+    writes: Vec<(PathBuf, PathBuf)>,
+}
+
+/// What the links to the renamed note need to know.
+struct Target {
+    node: usize,
+    new_stem: String,
+    new_title: String,
+    new_path: PathBuf,
+    /// Whether a link by name alone would find another note first
+    ambiguous: bool,
+    folder_changed: bool,
+    /// Old name, normalized, when mentions are rewritten
+    mention: Option<String>,
+    /// Normalized names of other notes that contain the old name
+    longer: Vec<String>,
+}
+
+/// Work out how renaming node `i` to `new_title` changes the vault.
+///
+/// `new_title` may start with folders relative to the vault, as in
+/// `projects/Plan`; without them the note stays in its folder. Explicit links
+/// to the note are rewritten to the new name and, with
+/// [`RenameOptions::mentions`], so are plain mentions of the old name.
+pub fn plan_rename(
+    data: &GraphData,
+    i: usize,
+    new_title: &str,
+    options: RenameOptions,
+) -> Result<RenamePlan> {
+    let node = &data.graph.nodes[i];
+    let (folder, title) = match new_title.trim().rsplit_once('/') {
+        Some((folder, title)) => (PathBuf::from(folder.trim_matches('/')), title),
+        None => (node.folder().to_path_buf(), new_title),
+    };
+    let title = normalize_title(title);
+    let stem = file_stem_for_title(&title)?;
+    if !folder
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(Error::invalid_title(
+            new_title,
+            "folder must be inside the vault",
+        ));
+    }
+    let new_path = folder.join(&stem);
+    if new_path == node.rel_path {
+        return Err(Error::invalid_title(
+            new_title,
+            "note already has this name",
+        ));
+    }
+
+    let root = data.root();
+    let mut moves = Vec::new();
+    for from in &node.paths {
+        let mut name = stem.clone();
+        if let Some(ext) = from.extension() {
+            name.push('.');
+            name.push_str(&ext.to_string_lossy());
+        }
+        let to = root.join(&folder).join(name);
+        if to.exists() && !same_file(from, &to) {
+            return Err(Error::NoteExists(to));
+        }
+        moves.push((from.clone(), to));
+    }
+
+    let old_name = normalize(&node.name);
+    let canon = canonicalize(&stem);
+    let target = Target {
+        node: i,
+        new_stem: stem,
+        new_title: title,
+        ambiguous: data
+            .graph
+            .nodes
+            .iter()
+            .enumerate()
+            .any(|(j, other)| j != i && canonicalize(&other.name) == canon),
+        folder_changed: folder != node.folder(),
+        new_path,
+        longer: data
+            .graph
+            .nodes
+            .iter()
+            .map(|other| normalize(&other.name))
+            .filter(|name| name.len() > old_name.len() && name.contains(old_name.as_str()))
+            .collect(),
+        mention: Some(old_name).filter(|_| options.mentions),
+    };
+
+    let mut edits = Vec::new();
+    for (j, other) in data.graph.nodes.iter().enumerate() {
+        let links_here = j == i
+            || data
+                .graph
+                .outgoing(j)
+                .iter()
+                .any(|e| e.to == i && e.kind == LinkKind::Explicit);
+        let mentions_here =
+            target.mention.is_some() && data.graph.outgoing(j).iter().any(|e| e.to == i);
+        if !links_here && !mentions_here {
+            continue;
+        }
+        for path in &other.paths {
+            let bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+            let Ok(old) = String::from_utf8(bytes) else {
+                continue;
+            };
+            let new = rewrite(data, j, path, &old, &target);
+            if new != old {
+                let path = match moves.iter().find(|(from, _)| from == path) {
+                    Some((_, to)) => to.clone(),
+                    None => path.clone(),
+                };
+                edits.push(FileEdit { path, old, new });
+            }
+        }
+    }
+    edits.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(RenamePlan {
+        root: root.to_path_buf(),
+        old_path: node.rel_path.clone(),
+        new_path: target.new_path,
+        moves,
+        edits,
+    })
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    matches!((fs::canonicalize(a), fs::canonicalize(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Contents of `path`, a file of node `from`, after the rename.
+fn rewrite(data: &GraphData, from: usize, path: &Path, text: &str, target: &Target) -> String {
+    let (frontmatter, body) = Frontmatter::split(text);
+    let header = &text[..text.len() - body.len()];
+    let folder = data.graph.nodes[from].folder();
+    let mut replacements: Vec<(Range<usize>, String)> = parse_links(body)
+        .into_iter()
+        .filter(|link| resolve_target(data, &link.target, folder) == Some((target.node, None)))
+        .filter_map(|link| {
+            let new = rewrite_link(&body[link.span.clone()], folder, target)?;
+            Some((link.span, new))
+        })
+        .collect();
+
+    if let Some(name) = &target.mention {
+        // like links, mentions in fenced code blocks are left alone
+        let mut in_fence = false;
+        let mut line_start = 0;
+        for line in body.split_inclusive('\n') {
+            let fence =
+                line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~");
+            in_fence ^= fence;
+            if fence || in_fence {
+                line_start += line.len();
+                continue;
+            }
+            let longer = find_names(line, &target.longer);
+            for found in find_names(line, std::slice::from_ref(name)) {
+                let inside = |r: &Range<usize>| r.start <= found.start && found.end <= r.end;
+                let span = found.start + line_start..found.end + line_start;
+                let overlaps = |r: &Range<usize>| r.start < span.end && span.start < r.end;
+                if !longer.iter().any(inside) && !replacements.iter().any(|(r, _)| overlaps(r)) {
+                    replacements.push((span, target.new_title.clone()));
+                }
+            }
+            line_start += line.len();
+        }
+    }
+
+    replacements.sort_by_key(|(r, _)| r.start);
+    let mut body = body.to_string();
+    for (range, new) in replacements.into_iter().rev() {
+        body.replace_range(range, &new);
+    }
+
+    let title_differs = target.new_stem != target.new_title;
+    let is_markdown = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("md"));
+    let header = match frontmatter {
+        Some(mut fm) if from == target.node && (title_differs || fm.get("title").is_some()) => {
+            fm.set("title", Value::String(target.new_title.clone()));
+            fm.render()
+        }
+        None if from == target.node && title_differs && is_markdown => {
+            let mut fm = Frontmatter::default();
+            fm.set("title", Value::String(target.new_title.clone()));
+            fm.render()
+        }
+        _ => header.to_string(),
+    };
+    header + &body
+}
+
+/// `raw`, an explicit link from a note in `folder` to the renamed note,
+/// pointing at the new name. Anchors and labels are kept.
+fn rewrite_link(raw: &str, folder: &Path, target: &Target) -> Option<String> {
+    if let Some(rest) = raw.strip_prefix("![[").or_else(|| raw.strip_prefix("[[")) {
+        let prefix = &raw[..raw.len() - rest.len()];
+        let inner = rest.strip_suffix("]]")?;
+        let split = inner.find(['#', '|']).unwrap_or(inner.len());
+        let (name, rest) = inner.split_at(split);
+        let new_name = if name.contains('/') || target.ambiguous {
+            slash_path(&target.new_path)
+        } else {
+            target.new_stem.clone()
+        };
+        return Some(format!("{}{}{}]]", prefix, new_name, rest));
+    }
+
+    // a Markdown link: `[label](dest)` with an optional title after `dest`
+    let open = raw.find("](")? + 2;
+    let dest = raw[open..].strip_suffix(')')?;
+    let trimmed = dest.trim_start();
+    let (wrapped, path, rest) = match trimmed.strip_prefix('<') {
+        Some(inner) => {
+            let end = inner.find('>')?;
+            (true, &inner[..end], &inner[end + 1..])
+        }
+        None => {
+            let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+            (false, &trimmed[..end], &trimmed[end..])
+        }
+    };
+    let split = path.find('#').unwrap_or(path.len());
+    let (path, anchor) = path.split_at(split);
+    let decoded = percent_decode(path);
+    let old = Path::new(&decoded);
+    let mut file_name = target.new_stem.clone();
+    if let Some(ext) = old.extension() {
+        file_name.push('.');
+        file_name.push_str(&ext.to_string_lossy());
+    }
+    let new = if target.folder_changed {
+        relative_path(folder, target.new_path.parent().unwrap_or(Path::new(""))).join(file_name)
+    } else {
+        old.with_file_name(file_name)
+    };
+    let mut new = slash_path(&new);
+    if !wrapped {
+        new = new.replace(' ', "%20");
+    }
+    if wrapped {
+        new = format!("<{}>", new);
+    }
+    Some(format!("{}{}{}{})", &raw[..open], new, anchor, rest))
+}
+
+pub(crate) fn slash_path(path: &Path) -> String {
+    let parts: Vec<_> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    parts.join("/")
+}
+
+/// Path of folder `to` relative to folder `from`, both relative to the vault.
+pub(crate) fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut out = PathBuf::new();
+    for _ in common..from.len() {
+        out.push("..");
+    }
+    for part in &to[common..] {
+        out.push(part);
+    }
+    out
+}
+
+impl RenamePlan {
+    /// The moves and edits of the plan in unified diff style, with paths
+    /// relative to the vault.
+    pub fn diff(&self) -> String {
+        let rel = |path: &Path| slash_path(path.strip_prefix(&self.root).unwrap_or(path));
+        let mut out = String::new();
+        for (from, to) in &self.moves {
+            let _ = writeln!(out, "rename {} -> {}", rel(from), rel(to));
+        }
+        for edit in &self.edits {
+            let _ = writeln!(out, "--- {}\n+++ {}", rel(&edit.path), rel(&edit.path));
+            line_diff(&edit.old, &edit.new, &mut out);
+        }
+        out
+    }
+}
+
+/// Append the lines that differ between `old` and `new` as hunks.
+fn line_diff(old: &str, new: &str, out: &mut String) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+    if old.len() == new.len() {
+        // the usual case: links rewritten in place, one hunk per line
+        for (n, (a, b)) in old.iter().zip(new).enumerate() {
+            if a != b {
+                let line = prefix + n + 1;
+                let _ = writeln!(out, "@@ -{} +{} @@\n-{}\n+{}", line, line, a, b);
+            }
+        }
+        return;
+    }
+    let _ = writeln!(
+        out,
+        "@@ -{},{} +{},{} @@",
+        prefix + 1,
+        old.len(),
+        prefix + 1,
+        new.len()
+    );
+    for line in old {
+        let _ = writeln!(out, "-{}", line);
+    }
+    for line in new {
+        let _ = writeln!(out, "+{}", line);
+    }
+}
+
+/// Carry out `plan`. A rename left unfinished by an earlier crash is
+/// completed first.
+pub fn apply(plan: &RenamePlan) -> Result<()> {
+    recover(&plan.root)?;
+    let journal = stage(plan)?;
+    finish(&plan.root, &journal)
+}
+
+/// Write the new contents and the journal, without changing the vault.
+fn stage(plan: &RenamePlan) -> Result<Journal> {
+    let root = &plan.root;
+    let dir = root.join(STATE_DIR).join(RENAME_DIR);
+    fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+    let rel = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+    let mut writes = Vec::new();
+    for (k, edit) in plan.edits.iter().enumerate() {
+        let staged = dir.join(k.to_string());
+        fs::write(&staged, &edit.new).map_err(|e| Error::io(&staged, e))?;
+        writes.push((rel(&staged), rel(&edit.path)));
+    }
+    let journal = Journal {
+        moves: plan.moves.iter().map(|(a, b)| (rel(a), rel(b))).collect(),
+        writes,
+    };
+    // the journal appears in one step, so it is either complete or missing
+    let path = dir.join(JOURNAL);
+    let tmp = dir.join(format!("{}.tmp", JOURNAL));
+    let json = serde_json::to_string(&journal).expect("journal serializes");
+    fs::write(&tmp, json).map_err(|e| Error::io(&tmp, e))?;
+    fs::rename(&tmp, &path).map_err(|e| Error::io(&path, e))?;
+    Ok(journal)
+}
+
+/// Perform the steps of `journal`, skipping those already done, and remove
+/// the staging folder.
+fn finish(root: &Path, journal: &Journal) -> Result<()> {
+    for (from, to) in &journal.moves {
+        let (from, to) = (root.join(from), root.join(to));
+        if !from.exists() || (to.exists() && !same_file(&from, &to)) {
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+        fs::rename(&from, &to).map_err(|e| Error::io(&from, e))?;
+    }
+    for (staged, to) in &journal.writes {
+        let (staged, to) = (root.join(staged), root.join(to));
+        if staged.exists() {
+            fs::rename(&staged, &to).map_err(|e| Error::io(&to, e))?;
+        }
+    }
+    let dir = root.join(STATE_DIR).join(RENAME_DIR);
+    fs::remove_dir_all(&dir).map_err(|e| Error::io(&dir, e))
+}
+
+/// Complete a rename that was interrupted in the vault at `root`. Returns
+/// whether there was one. Staged files without a journal belong to a rename
+/// that never started and are discarded.
+pub fn recover(root: &Path) -> Result<bool> {
+    let dir = root.join(STATE_DIR).join(RENAME_DIR);
+    if !dir.is_dir() {
+        return Ok(false);
+    }
+    let path = dir.join(JOURNAL);
+    let journal = match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str::<Journal>(&text).ok(),
+        Err(_) => None,
+    };
+    match journal {
+        Some(journal) => finish(root, &journal).map(|_| true),
+        None => fs::remove_dir_all(&dir)
+            .map(|_| false)
+            .map_err(|e| Error::io(&dir, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RenameOptions, apply, plan_rename, recover, stage};
+    use crate::error::Error;
+    use crate::graph::load_from;
+    use crate::testing::TempDir;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn vault(name: &str, files: &[(&str, &str)]) -> TempDir {
+        let root = TempDir::new(&format!("rename_{}", name));
+        for (path, text) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        root
+    }
+
+    fn read(root: &Path, path: &str) -> String {
+        fs::read_to_string(root.join(path)).unwrap()
+    }
+
+    #[test]
+    fn renames_files_and_rewrites_links() {
+        let root = vault(
+            "links",
+            &[
+                ("Power.md", "---\ntitle: Power\n---\nAbout power.\n"),
+                ("Power.pdf", "%PDF"),
+                (
+                    "Plan.md",
+                    "See [[Power]], [[power#Uses|uses]] and ![[Power]].\n\
+                     Also [this](Power.md#uses \"t\") and power.\n\
+                     ```\n[[Power]]\n```\n",
+                ),
+                ("projects/Deep.md", "Up: [[Power]] and [x](../Power.md).\n"),
+                ("Other.md", "Nuclear power only.\n"),
+                ("Nuclear Power.md", "Big [[Power]]\n"),
+            ],
+        );
+        let data = load_from(&root).unwrap();
+        let power = data.find("Power").unwrap();
+
+        let options = RenameOptions { mentions: true };
+        let plan = plan_rename(&data, power, "Energy Use", options).unwrap();
+        assert_eq!(plan.new_path, PathBuf::from("Energy Use"));
+        let diff = plan.diff();
+        assert!(
+            diff.contains("rename Power.pdf -> Energy Use.pdf\n"),
+            "{}",
+            diff
+        );
+        assert!(
+            diff.contains("-Up: [[Power]] and [x](../Power.md).\n"),
+            "{}",
+            diff
+        );
+        assert!(!root.join("Energy Use.md").exists());
+
+        apply(&plan).unwrap();
+        assert!(!root.join("Power.md").exists());
+        assert_eq!(read(&root, "Energy Use.pdf"), "%PDF");
+        assert_eq!(
+            read(&root, "Energy Use.md"),
+            "---\ntitle: Energy Use\n---\nAbout Energy Use.\n"
+        );
+        assert_eq!(
+            read(&root, "Plan.md"),
+            "See [[Energy Use]], [[Energy Use#Uses|uses]] and ![[Energy Use]].\n\
+             Also [this](Energy%20Use.md#uses \"t\") and Energy Use.\n\
+             ```\n[[Power]]\n```\n"
+        );
+        assert_eq!(
+            read(&root, "projects/Deep.md"),
+            "Up: [[Energy Use]] and [x](../Energy%20Use.md).\n"
+        );
+        // links, but mentions only inside the longer name
+        assert_eq!(read(&root, "Nuclear Power.md"), "Big [[Energy Use]]\n");
+        // does not link to the note, mentions stay
+        assert_eq!(read(&root, "Other.md"), "Nuclear power only.\n");
+        assert!(!root.join(".notes/rename").exists());
+
+        let data = load_from(&root).unwrap();
+        let note = data.find("Energy Use").unwrap();
+        assert!(matches!(
+            plan_rename(&data, note, "Plan", RenameOptions::default()),
+            Err(Error::NoteExists(_))
+        ));
+        assert!(plan_rename(&data, note, "Energy Use", RenameOptions::default()).is_err());
+        assert!(plan_rename(&data, note, "../Out", RenameOptions::default()).is_err());
+    }
+
+    #[test]
+    fn moves_between_folders() {
+        let root = vault(
+            "folders",
+            &[
+                ("a/Note.md", "Body\n"),
+                ("a/Index.md", "[[Note]] and [n](Note.md)\n"),
+                ("b/Note.md", "Other note\n"),
+            ],
+        );
+        let data = load_from(&root).unwrap();
+        let note = data
+            .graph
+            .nodes
+            .iter()
+            .position(|n| n.rel_path == Path::new("a/Note"))
+            .unwrap();
+        let plan = plan_rename(&data, note, "c/d/Note", RenameOptions::default()).unwrap();
+        apply(&plan).unwrap();
+        assert!(root.join("c/d/Note.md").is_file());
+        // another note has the name, so the wikilink needs the folder
+        assert_eq!(
+            read(&root, "a/Index.md"),
+            "[[c/d/Note]] and [n](../c/d/Note.md)\n"
+        );
+    }
+
+    #[test]
+    fn recovers_interrupted_rename() {
+        let root = vault(
+            "recover",
+            &[
+                ("Old.md", "Text\n"),
+                ("Old.txt", "Plain\n"),
+                ("Index.md", "[[Old]]\n"),
+            ],
+        );
+        let data = load_from(&root).unwrap();
+        let old = data.find("Old").unwrap();
+        let plan = plan_rename(&data, old, "New", RenameOptions::default()).unwrap();
+
+        // crash after staging: nothing in the vault has changed yet
+        stage(&plan).unwrap();
+        fs::rename(root.join("Old.md"), root.join("New.md")).unwrap();
+        assert!(recover(&root).unwrap());
+        assert!(!root.join("Old.txt").exists());
+        assert_eq!(read(&root, "New.txt"), "Plain\n");
+        assert_eq!(read(&root, "New.md"), "Text\n");
+        assert_eq!(read(&root, "Index.md"), "[[New]]\n");
+        assert!(!recover(&root).unwrap());
+
+        // staged files without a journal are dropped
+        fs::create_dir_all(root.join(".notes/rename")).unwrap();
+        fs::write(root.join(".notes/rename/0"), "x").unwrap();
+        assert!(!recover(&root).unwrap());
+        assert!(!root.join(".notes/rename").exists());
+    }
+}
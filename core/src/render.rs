@@ -0,0 +1,373 @@
+//! Rendering a note with its embeds expanded, as used by `notes show
+//! --render` and `notes export`.
+//!
+//! An embed of a note (`![[Note]]`) or of a part of one (`![[Note#Heading]]`,
+//! `![[Note#^block-id]]`) is replaced by that text, itself rendered. Embeds
+//! of other files in the vault, like `![[image.png]]`, become Markdown links
+//! to the file: images with `![name](path)`, anything else with
+//! `[name](path)`. Embeds that cannot be expanded are left as written and
+//! reported.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::anchors::find_section;
+use crate::config::config;
+use crate::error::{Error, Result};
+use crate::frontmatter::Frontmatter;
+use crate::graph::{GraphData, Node, normalize, resolve_target};
+use crate::links::{Link, parse_links};
+use crate::note::read_text;
+use crate::rename::{relative_path, slash_path};
+
+/// How many levels of embeds inside embeds are expanded by default.
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+/// Extensions of files embedded as images.
+const IMAGE_FORMATS: [&str; 8] = ["avif", "bmp", "gif", "jpeg", "jpg", "png", "svg", "webp"];
+
+/// Extensions of files whose text is embedded, besides the configured
+/// default format. Any other file is embedded as a link, even if it is text.
+const NOTE_FORMATS: [&str; 7] = ["adoc", "markdown", "md", "org", "rst", "tex", "txt"];
+
+/// Whether `path` is a note whose text can be embedded, judged by its
+/// extension alone.
+fn is_note_file(path: &Path) -> bool {
+    let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+    let ext = ext.to_ascii_lowercase();
+    !IMAGE_FORMATS.contains(&ext.as_str())
+        && (NOTE_FORMATS.contains(&ext.as_str()) || ext == config().default_format)
+}
+
+/// The file of `node` whose text is embedded: the one it is edited in if
+/// that is a note format, else the first file in a note format.
+fn note_file(node: &Node) -> Option<&PathBuf> {
+    node.text_file()
+        .filter(|p| is_note_file(p))
+        .or_else(|| node.paths.iter().find(|p| is_note_file(p)))
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Levels of embeds inside embeds to expand; 0 expands none
+    pub max_depth: usize,
+    /// Folder that links to embedded files are relative to, the vault root
+    /// if not given
+    pub base: Option<PathBuf>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            base: None,
+        }
+    }
+}
+
+/// An embed that was left as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmbedProblem {
+    /// The note or part embeds itself, through the embeds named in order
+    Cycle(Vec<String>),
+    /// The embed lies deeper than the maximum depth
+    TooDeep(String),
+    /// No note, file, heading or block has this name
+    NotFound(String),
+}
+
+impl fmt::Display for EmbedProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbedProblem::Cycle(chain) => {
+                write!(f, "embed cycle {} not expanded", chain.join(" > "))
+            }
+            EmbedProblem::TooDeep(target) => {
+                write!(f, "embed '{}' is nested too deeply, not expanded", target)
+            }
+            EmbedProblem::NotFound(target) => write!(f, "embedded '{}' not found", target),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rendered {
+    pub text: String,
+    /// Embeds left as written, in order of appearance
+    pub problems: Vec<EmbedProblem>,
+}
+
+/// The text of node `i` without its frontmatter, or of the heading or
+/// block `anchor` of it, with embeds expanded.
+pub fn render(
+    data: &GraphData,
+    i: usize,
+    anchor: Option<&str>,
+    options: &RenderOptions,
+) -> Result<Rendered> {
+    let base = options.base.as_deref().unwrap_or(data.root());
+    let mut renderer = Renderer {
+        data,
+        max_depth: options.max_depth,
+        base: std::path::absolute(base).map_err(|e| Error::io(base, e))?,
+        stack: Vec::new(),
+        problems: Vec::new(),
+    };
+    let node = &data.graph.nodes[i];
+    let text = match note_file(node) {
+        Some(file) => renderer.note_text(i, file, anchor)?,
+        None => None,
+    };
+    let text = text.ok_or_else(|| match anchor {
+        Some(anchor) => Error::AnchorNotFound {
+            note: node.name.clone(),
+            anchor: anchor.to_string(),
+        },
+        None => Error::NoteNotFound(node.rel_path.clone()),
+    })?;
+    Ok(Rendered {
+        text,
+        problems: renderer.problems,
+    })
+}
+
+struct Renderer<'a> {
+    data: &'a GraphData,
+    max_depth: usize,
+    /// Absolute folder that links to files are relative to
+    base: PathBuf,
+    /// Nodes and anchors being expanded, outermost first
+    stack: Vec<(usize, Option<String>)>,
+    problems: Vec<EmbedProblem>,
+}
+
+impl Renderer<'_> {
+    /// The rendered text of `path`, a file of node `j`, or of a part of it,
+    /// `None` if it has no such heading or block.
+    fn note_text(&mut self, j: usize, path: &Path, anchor: Option<&str>) -> Result<Option<String>> {
+        let text = read_text(path)?;
+        let part = match anchor {
+            Some(anchor) => match find_section(&text, anchor) {
+                Some(section) => &text[section.range],
+                None => return Ok(None),
+            },
+            None => Frontmatter::split(&text).1,
+        };
+        self.stack.push((j, anchor.map(str::to_string)));
+        let mut out = String::with_capacity(part.len());
+        let mut last = 0;
+        for link in parse_links(part).into_iter().filter(|l| l.embed) {
+            out.push_str(&part[last..link.span.start]);
+            match self.embed(&link)? {
+                Some(expanded) => out.push_str(&expanded),
+                None => out.push_str(&part[link.span.clone()]),
+            }
+            last = link.span.end;
+        }
+        out.push_str(&part[last..]);
+        self.stack.pop();
+        Ok(Some(out))
+    }
+
+    /// What the embed `link` in the innermost note expands to.
+    fn embed(&mut self, link: &Link) -> Result<Option<String>> {
+        let current = self.stack.last().expect("embeds are inside a note").0;
+        let name = match &link.anchor {
+            Some(anchor) => format!("{}#{}", link.target, anchor),
+            None => link.target.clone(),
+        };
+        let found = if link.target.is_empty() {
+            Some((current, None))
+        } else {
+            let folder = self.data.graph.nodes[current].folder();
+            resolve_file(self.data, &link.target, folder)
+        };
+        let Some((j, file)) = found else {
+            self.problems.push(EmbedProblem::NotFound(name));
+            return Ok(None);
+        };
+        let node = &self.data.graph.nodes[j];
+        // a file named by an extension other than a note's, or a note
+        // without text
+        let file = match file {
+            Some(file) => file,
+            None => match note_file(node) {
+                Some(file) => file.clone(),
+                None => node.paths[0].clone(),
+            },
+        };
+        if !is_note_file(&file) {
+            return self.asset_link(link, &file).map(Some);
+        }
+        let key = (j, link.anchor.clone());
+        let anchor = link.anchor.as_deref().map(normalize);
+        if let Some(k) = self
+            .stack
+            .iter()
+            .position(|(s, a)| *s == j && a.as_deref().map(normalize) == anchor)
+        {
+            let nodes = &self.data.graph.nodes;
+            let chain = self.stack[k..]
+                .iter()
+                .chain([&key])
+                .map(|(s, anchor)| match anchor {
+                    Some(anchor) => format!("{}#{}", nodes[*s].name, anchor),
+                    None => nodes[*s].name.clone(),
+                })
+                .collect();
+            self.problems.push(EmbedProblem::Cycle(chain));
+            return Ok(None);
+        }
+        if self.stack.len() > self.max_depth {
+            self.problems.push(EmbedProblem::TooDeep(name));
+            return Ok(None);
+        }
+        match self.note_text(j, &file, link.anchor.as_deref())? {
+            Some(text) => Ok(Some(text.trim_end_matches('\n').to_string())),
+            None => {
+                self.problems.push(EmbedProblem::NotFound(name));
+                Ok(None)
+            }
+        }
+    }
+
+    /// A Markdown link to the file `asset`, relative to the base folder.
+    fn asset_link(&self, link: &Link, asset: &Path) -> Result<String> {
+        let to = std::path::absolute(asset).map_err(|e| Error::io(asset, e))?;
+        let mut dest = slash_path(&relative_path(&self.base, &to)).replace(' ', "%20");
+        if let Some(anchor) = &link.anchor {
+            dest = format!("{}#{}", dest, anchor);
+        }
+        let file_name = asset
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let label = link.label.as_deref().unwrap_or(&file_name);
+        let image = asset
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_FORMATS.contains(&e.to_ascii_lowercase().as_str()));
+        Ok(format!(
+            "{}[{}]({})",
+            if image { "!" } else { "" },
+            label,
+            dest
+        ))
+    }
+}
+
+/// The node `target` names and, if it names one of the node's files by its
+/// extension (`image.png`), that file.
+fn resolve_file(data: &GraphData, target: &str, folder: &Path) -> Option<(usize, Option<PathBuf>)> {
+    let path = Path::new(target);
+    if let Some(ext) = path.extension().and_then(|e| e.to_str())
+        && let Some((j, _)) =
+            resolve_target(data, &path.with_extension("").to_string_lossy(), folder)
+    {
+        let file = data.graph.nodes[j]
+            .paths
+            .iter()
+            .find(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case(ext)));
+        if let Some(file) = file {
+            return Some((j, Some(file.clone())));
+        }
+    }
+    resolve_target(data, target, folder).map(|(j, _)| (j, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmbedProblem, RenderOptions, render};
+    use crate::graph::load_from;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn expands_embeds() {
+        let root = TempDir::new("render");
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::write(
+            root.join("Main.md"),
+            "---\ntags: [x]\n---\nStart\n![[Part]]\n![[Guide#Setup]]\n![[my image.png]]\n![[Report|the report]]\n![[diagram.svg]] ![[plain]]\n![[Missing]] ![[Guide#Nowhere]]\n",
+        )
+        .unwrap();
+        fs::write(root.join("Part.md"), "Part text with ![[Inner]].\n").unwrap();
+        fs::write(root.join("Inner.md"), "inner").unwrap();
+        fs::write(
+            root.join("Guide.md"),
+            "# Guide\n## Setup\nInstall.\n\n## Usage\nRun.\n",
+        )
+        .unwrap();
+        fs::write(root.join("assets/my image.png"), [0x89, 0xff, 0x00]).unwrap();
+        fs::write(root.join("Report.pdf"), [0xff, 0x00]).unwrap();
+        fs::write(root.join("diagram.svg"), "<svg></svg>\n").unwrap();
+        fs::write(root.join("plain.png"), "valid UTF-8").unwrap();
+
+        let data = load_from(&root).unwrap();
+        let main = data.find("Main").unwrap();
+        let rendered = render(&data, main, None, &RenderOptions::default()).unwrap();
+        assert_eq!(
+            rendered.text,
+            "Start\nPart text with inner.\n## Setup\nInstall.\n![my image.png](assets/my%20image.png)\n[the report](Report.pdf)\n![diagram.svg](diagram.svg) ![plain.png](plain.png)\n![[Missing]] ![[Guide#Nowhere]]\n"
+        );
+        assert_eq!(
+            rendered.problems,
+            [
+                EmbedProblem::NotFound("Missing".into()),
+                EmbedProblem::NotFound("Guide#Nowhere".into())
+            ]
+        );
+
+        let options = RenderOptions {
+            max_depth: 1,
+            base: Some(root.join("assets")),
+        };
+        let rendered = render(&data, main, None, &options).unwrap();
+        assert!(rendered.text.contains("Part text with ![[Inner]].\n"));
+        assert!(rendered.text.contains("![my image.png](my%20image.png)\n"));
+        assert!(rendered.text.contains("[the report](../Report.pdf)\n"));
+        assert_eq!(rendered.problems[0], EmbedProblem::TooDeep("Inner".into()));
+
+        let guide = data.find("Guide").unwrap();
+        let rendered = render(&data, guide, Some("Usage"), &RenderOptions::default()).unwrap();
+        assert_eq!(rendered.text, "## Usage\nRun.\n");
+        assert!(render(&data, guide, Some("Other"), &RenderOptions::default()).is_err());
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let root = TempDir::new("render_cycle");
+        fs::write(root.join("A.md"), "a ![[B]]\n").unwrap();
+        fs::write(root.join("B.md"), "b ![[A]] ![[#Self]]\n").unwrap();
+        fs::write(
+            root.join("C.md"),
+            "# One\n![[#^two]]\n\ntwo ![[#One]] ^two\n",
+        )
+        .unwrap();
+
+        let data = load_from(&root).unwrap();
+        let a = data.find("A").unwrap();
+        let rendered = render(&data, a, None, &RenderOptions::default()).unwrap();
+        assert_eq!(rendered.text, "a b ![[A]] ![[#Self]]\n");
+        assert_eq!(
+            rendered.problems,
+            [
+                EmbedProblem::Cycle(vec!["A".into(), "B".into(), "A".into()]),
+                EmbedProblem::NotFound("#Self".into())
+            ]
+        );
+
+        // a part of a note may embed another part, but not one containing it
+        let c = data.find("C").unwrap();
+        let rendered = render(&data, c, Some("^two"), &RenderOptions::default()).unwrap();
+        assert_eq!(
+            rendered.problems,
+            [
+                EmbedProblem::Cycle(vec!["C#^two".into(), "C#One".into(), "C#^two".into()]),
+                EmbedProblem::Cycle(vec!["C#One".into(), "C#One".into()])
+            ]
+        );
+    }
+}
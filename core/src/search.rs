@@ -0,0 +1,596 @@
+//! Full-text search over the notes of a vault, as used by `notes search`.
+//!
+//! A [`SearchIndex`] is an inverted index over the titles, aliases, tags and
+//! bodies of the notes in a [`GraphData`]. It is built once with
+//! [`SearchIndex::build`] and then kept in step with the graph by passing the
+//! [`Changes`] of every refresh to [`SearchIndex::update`].
+//!
+//! Queries are parsed by [`Query::parse`]: plain words must all occur,
+//! `"quoted words"` must occur in that order, and `tag:`, `path:` and
+//! `format:` restrict the notes searched. Hits are ranked with BM25, with
+//! words in titles and aliases counting more than words in the body.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::frontmatter::Frontmatter;
+use crate::graph::{Changes, GraphData, Node};
+use crate::tags::tag_matches;
+use crate::vault::glob_match;
+
+/// BM25 saturation of repeated terms.
+const K1: f64 = 1.2;
+/// BM25 normalization by document length.
+const B: f64 = 0.75;
+/// Weight of a term in the title or an alias, relative to the body.
+const TITLE_WEIGHT: f64 = 3.0;
+/// Weight of a term in a tag, relative to the body.
+const TAG_WEIGHT: f64 = 2.0;
+/// Positions left free between fields so phrases do not span them.
+const FIELD_GAP: u32 = 8;
+/// Snippets shown per hit.
+const MAX_SNIPPETS: usize = 3;
+/// Longest snippet, in characters, before it is cut around its first match.
+const MAX_SNIPPET_CHARS: usize = 160;
+
+/// A parsed search query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    /// Words and phrases that must all occur, each as its lowercase tokens
+    pub phrases: Vec<Vec<String>>,
+    /// `tag:` filters; a note must have a tag matching each of these, see
+    /// [`tag_matches`]
+    pub tags: Vec<String>,
+    /// `path:` filters; a note must be in each folder, or match each glob
+    pub paths: Vec<String>,
+    /// `format:` filters; a note must have a file in each format
+    pub formats: Vec<String>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Query {
+        let mut parsed = Query::default();
+        let mut rest = query.trim_start();
+        while !rest.is_empty() {
+            let (word, quoted, next) = if let Some(inner) = rest.strip_prefix('"') {
+                match inner.find('"') {
+                    Some(end) => (&inner[..end], true, &inner[end + 1..]),
+                    None => (inner, true, ""),
+                }
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], false, &rest[end..])
+            };
+            rest = next.trim_start();
+            if !quoted && let Some((key, value)) = word.split_once(':') {
+                let value = value.to_string();
+                match key.to_ascii_lowercase().as_str() {
+                    "tag" if !value.is_empty() => {
+                        parsed.tags.push(value.trim_start_matches('#').to_string());
+                        continue;
+                    }
+                    "path" if !value.is_empty() => {
+                        parsed.paths.push(value);
+                        continue;
+                    }
+                    "format" if !value.is_empty() => {
+                        parsed
+                            .formats
+                            .push(value.trim_start_matches('.').to_ascii_lowercase());
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            // a word like `x-ray` is the phrase `x ray`
+            let tokens: Vec<String> = tokenize(word).map(|(_, token)| token).collect();
+            if !tokens.is_empty() {
+                parsed.phrases.push(tokens);
+            }
+        }
+        parsed
+    }
+
+    /// Whether the query has neither words nor filters.
+    pub fn is_empty(&self) -> bool {
+        self.phrases.is_empty()
+            && self.tags.is_empty()
+            && self.paths.is_empty()
+            && self.formats.is_empty()
+    }
+
+    fn accepts(&self, doc: &Doc) -> bool {
+        self.tags
+            .iter()
+            .all(|tag| doc.tags.iter().any(|t| tag_matches(t, tag)))
+            && self.paths.iter().all(|path| {
+                if path.contains(['*', '?']) {
+                    glob_match(path, &doc.rel_path.to_string_lossy())
+                } else {
+                    doc.rel_path.starts_with(path.trim_end_matches('/'))
+                }
+            })
+            && self.formats.iter().all(|f| doc.formats.contains(f))
+    }
+}
+
+/// One line of a note that matches the query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snippet {
+    /// File the line is in, relative to the vault
+    pub path: PathBuf,
+    /// Line number in the file, starting at 1
+    pub line: usize,
+    /// The line, shortened around the first match if it is long
+    pub text: String,
+    /// Byte ranges of `text` that match the query
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// A note matching a query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hit {
+    pub name: String,
+    /// Path relative to the vault without extension
+    pub path: PathBuf,
+    /// BM25 score; 0 when the query only has filters
+    pub score: f64,
+    pub snippets: Vec<Snippet>,
+}
+
+/// Text file of an indexed note.
+#[derive(Debug, Clone)]
+struct DocFile {
+    /// Path relative to the vault
+    path: PathBuf,
+    /// Number of frontmatter lines before the body
+    skipped_lines: usize,
+    body: String,
+}
+
+#[derive(Debug, Clone)]
+struct Doc {
+    name: String,
+    rel_path: PathBuf,
+    tags: Vec<String>,
+    formats: Vec<String>,
+    files: Vec<DocFile>,
+    /// Number of indexed tokens, over all fields
+    len: usize,
+    /// Distinct terms, to find the postings to drop when the note goes
+    terms: Vec<String>,
+}
+
+/// Occurrences of a term in one document.
+#[derive(Debug, Clone, Default)]
+struct Posting {
+    /// Occurrences weighted by the field they are in
+    weight: f64,
+    /// Positions in the document's token stream, ascending
+    positions: Vec<u32>,
+}
+
+/// Inverted index over the notes of a vault.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    root: PathBuf,
+    /// Documents by slot; slots of removed notes are reused
+    docs: Vec<Option<Doc>>,
+    free: Vec<usize>,
+    /// Slot of each note, by [`Node::rel_path`]
+    slots: HashMap<PathBuf, usize>,
+    postings: HashMap<String, HashMap<usize, Posting>>,
+    total_len: usize,
+}
+
+/// Lowercase tokens of `text` with the byte offset each starts at. Tokens are
+/// runs of letters and digits.
+fn tokenize(text: &str) -> impl Iterator<Item = (Range<usize>, String)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        while chars.next_if(|(_, c)| !c.is_alphanumeric()).is_some() {}
+        let (start, _) = *chars.peek()?;
+        let mut end = start;
+        let mut token = String::new();
+        while let Some((i, c)) = chars.next_if(|(_, c)| c.is_alphanumeric()) {
+            token.extend(c.to_lowercase());
+            end = i + c.len_utf8();
+        }
+        Some((start..end, token))
+    })
+}
+
+impl SearchIndex {
+    /// Index every note of `data`, reading their text files.
+    pub fn build(data: &GraphData) -> Result<SearchIndex> {
+        let mut index = SearchIndex {
+            root: data.root().to_path_buf(),
+            ..SearchIndex::default()
+        };
+        for node in &data.graph.nodes {
+            index.insert(node)?;
+        }
+        Ok(index)
+    }
+
+    /// Bring the index up to date with the notes in `changes`, as returned by
+    /// the refresh that produced `data`.
+    pub fn update(&mut self, data: &GraphData, changes: &Changes) -> Result<()> {
+        for rel_path in &changes.removed {
+            self.remove(rel_path);
+        }
+        let touched: HashSet<&PathBuf> = changes.added.iter().chain(&changes.modified).collect();
+        for node in &data.graph.nodes {
+            if touched.contains(&node.rel_path) {
+                self.remove(&node.rel_path);
+                self.insert(node)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of indexed notes.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    fn insert(&mut self, node: &Node) -> Result<()> {
+        let mut files = Vec::new();
+        for path in &node.paths {
+            let bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+            let Ok(text) = String::from_utf8(bytes) else {
+                continue;
+            };
+            let (_, body) = Frontmatter::split(&text);
+            let skipped_lines = text[..text.len() - body.len()].matches('\n').count();
+            files.push(DocFile {
+                path: path.strip_prefix(&self.root).unwrap_or(path).to_path_buf(),
+                skipped_lines,
+                body: body.to_string(),
+            });
+        }
+
+        let mut terms: HashMap<String, Posting> = HashMap::new();
+        let mut position = 0;
+        let mut len = 0;
+        let mut add_field = |text: &str, weight: f64| {
+            for (_, token) in tokenize(text) {
+                let posting = terms.entry(token).or_default();
+                posting.weight += weight;
+                posting.positions.push(position);
+                position += 1;
+                len += 1;
+            }
+            position += FIELD_GAP;
+        };
+        add_field(&node.name, TITLE_WEIGHT);
+        for alias in &node.aliases {
+            add_field(alias, TITLE_WEIGHT);
+        }
+        for tag in &node.tags {
+            add_field(tag, TAG_WEIGHT);
+        }
+        for file in &files {
+            add_field(&file.body, 1.0);
+        }
+
+        let slot = self.free.pop().unwrap_or_else(|| {
+            self.docs.push(None);
+            self.docs.len() - 1
+        });
+        let mut doc_terms = Vec::with_capacity(terms.len());
+        for (term, posting) in terms {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(slot, posting);
+            doc_terms.push(term);
+        }
+        self.total_len += len;
+        self.slots.insert(node.rel_path.clone(), slot);
+        self.docs[slot] = Some(Doc {
+            name: node.name.clone(),
+            rel_path: node.rel_path.clone(),
+            tags: node.tags.clone(),
+            formats: node.formats(),
+            files,
+            len,
+            terms: doc_terms,
+        });
+        Ok(())
+    }
+
+    fn remove(&mut self, rel_path: &Path) {
+        let Some(slot) = self.slots.remove(rel_path) else {
+            return;
+        };
+        let Some(doc) = self.docs[slot].take() else {
+            return;
+        };
+        for term in &doc.terms {
+            if let Some(postings) = self.postings.get_mut(term) {
+                postings.remove(&slot);
+                if postings.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        self.total_len -= doc.len;
+        self.free.push(slot);
+    }
+
+    /// Notes matching `query`, best first, at most `limit` of them. Notes
+    /// with equal scores are ordered by path.
+    pub fn search(&self, query: &Query, limit: usize) -> Vec<Hit> {
+        let mut hits: Vec<Hit> = self
+            .candidates(query)
+            .into_iter()
+            .filter_map(|slot| {
+                let doc = self.docs[slot].as_ref()?;
+                if !query.accepts(doc) || !self.has_phrases(slot, &query.phrases) {
+                    return None;
+                }
+                Some(Hit {
+                    name: doc.name.clone(),
+                    path: doc.rel_path.clone(),
+                    score: self.score(slot, doc, &query.phrases),
+                    snippets: Vec::new(),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        hits.truncate(limit);
+        for hit in &mut hits {
+            let doc = self.docs[self.slots[&hit.path]].as_ref().unwrap();
+            hit.snippets = snippets(doc, &query.phrases);
+        }
+        hits
+    }
+
+    /// Slots of the documents containing every term of the query, or of all
+    /// documents if it has none.
+    fn candidates(&self, query: &Query) -> Vec<usize> {
+        let mut terms = query.phrases.iter().flatten();
+        let Some(first) = terms.next() else {
+            return self.slots.values().copied().collect();
+        };
+        let Some(postings) = self.postings.get(first) else {
+            return Vec::new();
+        };
+        let mut slots: Vec<usize> = postings.keys().copied().collect();
+        for term in terms {
+            let Some(postings) = self.postings.get(term) else {
+                return Vec::new();
+            };
+            slots.retain(|slot| postings.contains_key(slot));
+        }
+        slots
+    }
+
+    /// Whether every phrase occurs in document `slot` with its tokens in
+    /// order and next to each other.
+    fn has_phrases(&self, slot: usize, phrases: &[Vec<String>]) -> bool {
+        phrases.iter().filter(|p| p.len() > 1).all(|phrase| {
+            let positions: Vec<&[u32]> = phrase
+                .iter()
+                .map(|term| self.postings[term][&slot].positions.as_slice())
+                .collect();
+            positions[0].iter().any(|&start| {
+                positions[1..]
+                    .iter()
+                    .zip(1..)
+                    .all(|(p, offset)| p.binary_search(&(start + offset)).is_ok())
+            })
+        })
+    }
+
+    fn score(&self, slot: usize, doc: &Doc, phrases: &[Vec<String>]) -> f64 {
+        let n = self.slots.len() as f64;
+        let average_len = self.total_len as f64 / n.max(1.0);
+        let norm = K1 * (1.0 - B + B * doc.len as f64 / average_len.max(1.0));
+        phrases
+            .iter()
+            .flatten()
+            .map(|term| {
+                let postings = &self.postings[term];
+                let df = postings.len() as f64;
+                let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+                let tf = postings[&slot].weight;
+                idf * tf * (K1 + 1.0) / (tf + norm)
+            })
+            .sum()
+    }
+}
+
+/// Lines of the document's bodies where a word or phrase of the query
+/// occurs.
+fn snippets(doc: &Doc, phrases: &[Vec<String>]) -> Vec<Snippet> {
+    let mut out = Vec::new();
+    if phrases.is_empty() {
+        return out;
+    }
+    for file in &doc.files {
+        for (i, line) in file.body.lines().enumerate() {
+            let tokens: Vec<(Range<usize>, String)> = tokenize(line).collect();
+            let mut highlights = Vec::new();
+            for start in 0..tokens.len() {
+                for phrase in phrases {
+                    let end = start + phrase.len();
+                    if end <= tokens.len()
+                        && tokens[start..end].iter().map(|(_, t)| t).eq(phrase.iter())
+                    {
+                        highlights.push(tokens[start].0.start..tokens[end - 1].0.end);
+                    }
+                }
+            }
+            if highlights.is_empty() {
+                continue;
+            }
+            let (text, highlights) = shorten(line, highlights);
+            out.push(Snippet {
+                path: file.path.clone(),
+                line: file.skipped_lines + i + 1,
+                text,
+                highlights,
+            });
+            if out.len() == MAX_SNIPPETS {
+                return out;
+            }
+        }
+    }
+    out
+}
+
+/// Sort the highlights of a line and merge those that overlap, then cut the
+/// line to a window around its first highlight if it is long, shifting the
+/// highlights to match. Cut ends are marked with `…`.
+pub(crate) fn shorten(
+    line: &str,
+    mut highlights: Vec<Range<usize>>,
+) -> (String, Vec<Range<usize>>) {
+    highlights.sort_by_key(|r| (r.start, r.end));
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(highlights.len());
+    for range in highlights {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    let highlights = merged;
+    let line = line.trim_end();
+    if line.chars().count() <= MAX_SNIPPET_CHARS {
+        return (line.to_string(), highlights);
+    }
+    let lead = MAX_SNIPPET_CHARS / 4;
+    let first = highlights[0].start;
+    let start = line[..first]
+        .char_indices()
+        .rev()
+        .nth(lead.saturating_sub(1))
+        .map_or(0, |(i, _)| i);
+    let end = line[start..]
+        .char_indices()
+        .nth(MAX_SNIPPET_CHARS)
+        .map_or(line.len(), |(i, _)| start + i);
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if end < line.len() { "…" } else { "" };
+    let shift = |i: usize| i - start + prefix.len();
+    let highlights = highlights
+        .into_iter()
+        .filter(|r| r.start >= start && r.end <= end)
+        .map(|r| shift(r.start)..shift(r.end))
+        .collect();
+    (
+        format!("{}{}{}", prefix, &line[start..end], suffix),
+        highlights,
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::{Query, SearchIndex, shorten, tokenize};
+    use crate::graph::{load_from, refresh};
+    use crate::testing::TempDir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn parse_query() {
+        let query = Query::parse("tag:#work  \"Road map\" x-ray path:projects/ format:.PDF kind:x");
+        assert_eq!(
+            query.phrases,
+            [vec!["road", "map"], vec!["x", "ray"], vec!["kind", "x"]]
+        );
+        assert_eq!(query.tags, ["work"]);
+        assert_eq!(query.paths, ["projects/"]);
+        assert_eq!(query.formats, ["pdf"]);
+        let tokens: Vec<_> = tokenize("Größe, 42!").collect();
+        assert_eq!(
+            tokens,
+            [(0..7, "größe".to_string()), (9..11, "42".to_string())]
+        );
+    }
+
+    #[test]
+    fn ranks_filters_and_updates() {
+        let root = TempDir::new("search");
+        fs::create_dir_all(root.join("projects")).unwrap();
+        fs::write(
+            root.join("Reactor.md"),
+            "---\ntags: [physics]\n---\nThe reactor core.\nCooling the core matters.\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("projects/Plan.md"),
+            "Build a reactor.\nThe core plan is simple.\n",
+        )
+        .unwrap();
+        fs::write(root.join("Other.md"), "Nothing to see.\n").unwrap();
+
+        let mut data = load_from(&root).unwrap();
+        let mut index = SearchIndex::build(&data).unwrap();
+        assert_eq!(index.len(), 3);
+
+        let hits = index.search(&Query::parse("reactor"), 10);
+        let names: Vec<_> = hits.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["Reactor", "Plan"]);
+        assert_eq!(hits[0].snippets[0].line, 4);
+        assert_eq!(hits[0].snippets[0].path, PathBuf::from("Reactor.md"));
+        assert_eq!(hits[0].snippets[0].highlights, [4..11]);
+
+        let hits = index.search(&Query::parse("\"the core\""), 10);
+        let names: Vec<_> = hits.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["Reactor", "Plan"]);
+        assert_eq!(hits[0].snippets.len(), 1);
+        assert_eq!(hits[0].snippets[0].line, 5);
+        let hits = index.search(&Query::parse("\"core the\""), 10);
+        assert!(hits.is_empty());
+
+        let names = |q: &str| -> Vec<String> {
+            index
+                .search(&Query::parse(q), 10)
+                .into_iter()
+                .map(|h| h.name)
+                .collect()
+        };
+        assert_eq!(names("core tag:Physics"), ["Reactor"]);
+        assert_eq!(names("core path:projects"), ["Plan"]);
+        assert_eq!(names("path:*/P*"), ["Plan"]);
+        assert_eq!(names("format:md"), ["Other", "Reactor", "Plan"]);
+        assert!(names("core format:pdf").is_empty());
+
+        fs::write(root.join("Other.md"), "A reactor after all.\n").unwrap();
+        fs::remove_file(root.join("projects/Plan.md")).unwrap();
+        let changes = refresh(&mut data).unwrap();
+        index.update(&data, &changes).unwrap();
+        assert_eq!(index.len(), 2);
+        let names: Vec<String> = index
+            .search(&Query::parse("reactor"), 10)
+            .into_iter()
+            .map(|h| h.name)
+            .collect();
+        assert_eq!(names, ["Reactor", "Other"]);
+    }
+
+    #[test]
+    fn shortens_long_lines() {
+        let line = format!("{} needle {}", "a ".repeat(100), "b ".repeat(100));
+        let (text, highlights) = shorten(&line, vec![201..207]);
+        assert!(text.starts_with('…') && text.ends_with('…'));
+        assert_eq!(&text[highlights[0].clone()], "needle");
+    }
+}
@@ -0,0 +1,240 @@
+//! Tags of notes, as used by `notes tags` and the tag filters of `notes
+//! list`, `notes search` and `notes tasks`.
+//!
+//! A note's tags are those listed under `tags:` in its frontmatter and the
+//! inline `#tags` of its body. Tags nest with `/`: a note tagged
+//! `#area/sub` counts towards `area` too, and the pattern `area/*` matches
+//! both.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::graph::Graph;
+
+/// The tag at the start of `s`, the text after a `#`: letters, digits, `-`,
+/// `_` and `/`, with at least one letter. Trailing `/` are not part of it.
+pub(crate) fn parse_tag(s: &str) -> Option<&str> {
+    let end = s
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '/')))
+        .unwrap_or(s.len());
+    let tag = s[..end].trim_end_matches('/');
+    (!tag.starts_with('/') && tag.chars().any(char::is_alphabetic)).then_some(tag)
+}
+
+/// Inline `#tags` of `body`, in order of appearance and without duplicates.
+/// A tag starts a line or follows whitespace; tags in code are skipped.
+pub(crate) fn inline_tags(body: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let mut in_code = false;
+        let mut prev = None;
+        for (i, c) in line.char_indices() {
+            if c == '`' {
+                in_code = !in_code;
+            } else if c == '#'
+                && !in_code
+                && prev.is_none_or(char::is_whitespace)
+                && let Some(tag) = parse_tag(&line[i + 1..])
+                && !out.iter().any(|t| t == tag)
+            {
+                out.push(tag.to_string());
+            }
+            prev = Some(c);
+        }
+    }
+    out
+}
+
+/// Whether `tag` matches `pattern`: the same tag ignoring case, or with
+/// `area/*` the tag `area` and every tag below it. `*` matches every tag.
+pub fn tag_matches(tag: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim_start_matches('#');
+    if pattern == "*" {
+        return true;
+    }
+    let Some(parent) = pattern.strip_suffix("/*") else {
+        return tag.eq_ignore_ascii_case(pattern);
+    };
+    match tag.get(..parent.len()) {
+        Some(head) => {
+            head.eq_ignore_ascii_case(parent)
+                && (tag.len() == parent.len() || tag[parent.len()..].starts_with('/'))
+        }
+        None => false,
+    }
+}
+
+/// The tag and the tags above it, e.g. `a`, `a/b` and `a/b/c` for `a/b/c`.
+fn with_parents(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices('/')
+        .map(|(i, _)| &tag[..i])
+        .chain(std::iter::once(tag))
+}
+
+/// A tag with the number of notes using it and the tags below it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagCount {
+    /// The full tag, e.g. `area/sub`
+    pub tag: String,
+    /// Notes with this tag or a tag below it
+    pub notes: usize,
+    pub children: Vec<TagCount>,
+}
+
+/// Every tag of the graph's notes as a tree, sorted by name. Tags differing
+/// only in case are counted together under the first spelling found.
+pub fn tag_tree(graph: &Graph) -> Vec<TagCount> {
+    // lowercase tag -> spelling and number of notes
+    let mut counts: BTreeMap<String, (String, usize)> = BTreeMap::new();
+    for node in &graph.nodes {
+        let mut seen: Vec<String> = Vec::new();
+        for tag in node.tags.iter().flat_map(|tag| with_parents(tag)) {
+            let key = tag.to_lowercase();
+            if seen.contains(&key) {
+                continue;
+            }
+            counts.entry(key.clone()).or_insert((tag.to_string(), 0)).1 += 1;
+            seen.push(key);
+        }
+    }
+    build_tree(&counts, None)
+}
+
+fn build_tree(counts: &BTreeMap<String, (String, usize)>, parent: Option<&str>) -> Vec<TagCount> {
+    counts
+        .iter()
+        .filter(|(key, _)| key.rsplit_once('/').map(|(head, _)| head) == parent)
+        .map(|(key, (tag, notes))| TagCount {
+            tag: tag.clone(),
+            notes: *notes,
+            children: build_tree(counts, Some(key)),
+        })
+        .collect()
+}
+
+/// Tags as extra nodes of the graph view, linked to the notes using them
+/// and to the tags they are nested in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagNodes {
+    /// Every tag and the tags above it, sorted, in the first spelling found
+    pub tags: Vec<String>,
+    /// Links from a note (index into `Graph::nodes`) to a tag (index into
+    /// `tags`)
+    pub notes: Vec<(usize, usize)>,
+    /// Links from a tag to the tag it is nested in, both indices into `tags`
+    pub nested: Vec<(usize, usize)>,
+}
+
+pub fn tag_nodes(graph: &Graph) -> TagNodes {
+    let mut by_key: BTreeMap<String, String> = BTreeMap::new();
+    for node in &graph.nodes {
+        for tag in node.tags.iter().flat_map(|tag| with_parents(tag)) {
+            by_key
+                .entry(tag.to_lowercase())
+                .or_insert_with(|| tag.to_string());
+        }
+    }
+    let keys: Vec<&String> = by_key.keys().collect();
+    let index = |tag: &str| keys.binary_search(&&tag.to_lowercase()).ok();
+    let mut out = TagNodes::default();
+    for (i, node) in graph.nodes.iter().enumerate() {
+        for tag in &node.tags {
+            if let Some(k) = index(tag)
+                && !out.notes.contains(&(i, k))
+            {
+                out.notes.push((i, k));
+            }
+        }
+    }
+    for (k, key) in keys.iter().enumerate() {
+        if let Some((head, _)) = key.rsplit_once('/')
+            && let Some(parent) = index(head)
+        {
+            out.nested.push((k, parent));
+        }
+    }
+    out.tags = by_key.into_values().collect();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TagNodes, inline_tags, parse_tag, tag_matches, tag_nodes, tag_tree};
+    use crate::graph::{Graph, Node};
+    use std::path::PathBuf;
+
+    #[test]
+    fn finds_inline_tags() {
+        let body = "#start and #area/sub, #area/sub again\n# Heading\nissue#3 #42 `#code` (#x)\n```\n#fenced\n```\nend #last/\n";
+        assert_eq!(inline_tags(body), ["start", "area/sub", "last"]);
+        assert_eq!(parse_tag("a-b_c/d."), Some("a-b_c/d"));
+        assert_eq!(parse_tag("2026"), None);
+        assert_eq!(parse_tag("/x"), None);
+    }
+
+    #[test]
+    fn matches_tag_patterns() {
+        assert!(tag_matches("Work", "work"));
+        assert!(!tag_matches("work/ops", "work"));
+        assert!(tag_matches("work/ops", "#work/*"));
+        assert!(tag_matches("work", "work/*"));
+        assert!(tag_matches("Work/Ops/x", "work/ops/*"));
+        assert!(!tag_matches("workshop", "work/*"));
+        assert!(!tag_matches("wo", "work/*"));
+        assert!(tag_matches("anything", "*"));
+    }
+
+    #[test]
+    fn counts_tags_as_a_tree() {
+        let node = |name: &str, tags: &[&str]| Node {
+            name: name.into(),
+            rel_path: PathBuf::from(name),
+            paths: Vec::new(),
+            aliases: Vec::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            links: 0,
+        };
+        let mut graph = Graph::default();
+        graph.nodes = vec![
+            node("A", &["area/sub", "area/other", "misc"]),
+            node("B", &["Area/sub"]),
+            node("C", &[]),
+        ];
+        let tree = tag_tree(&graph);
+        let flat: Vec<(&str, usize, usize)> = tree
+            .iter()
+            .map(|t| (t.tag.as_str(), t.notes, t.children.len()))
+            .collect();
+        assert_eq!(flat, [("area", 2, 2), ("misc", 1, 0)]);
+        let children: Vec<(&str, usize)> = tree[0]
+            .children
+            .iter()
+            .map(|t| (t.tag.as_str(), t.notes))
+            .collect();
+        assert_eq!(children, [("area/other", 1), ("area/sub", 2)]);
+
+        assert_eq!(
+            tag_nodes(&graph),
+            TagNodes {
+                tags: vec![
+                    "area".into(),
+                    "area/other".into(),
+                    "area/sub".into(),
+                    "misc".into()
+                ],
+                notes: vec![(0, 2), (0, 1), (0, 3), (1, 2)],
+                nested: vec![(1, 0), (2, 0)],
+            }
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Tasks written as Markdown checkboxes, as used by `notes tasks`.
+//!
+//! Every list item starting with `[ ]` or `[x]` is a task. Its text may
+//! carry a due date (`📅 2026-10-20` or `due:2026-10-20`), a priority (`⏫`,
+//! `🔼`, `🔽` or `priority:high`) and `#tags`. Checkboxes in fenced code
+//! blocks and in the frontmatter are left alone.
+//!
+//! Tasks are identified by a short hash of their file and text, so an id
+//! stays the same while lines are added around the task or it is checked.
+
+use std::fmt;
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use twox_hash::XxHash64;
+
+use crate::error::{Error, Result};
+use crate::frontmatter::Frontmatter;
+use crate::graph::GraphData;
+use crate::tags::{parse_tag, tag_matches};
+
+/// Whether a task is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Open,
+    Done,
+}
+
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "open" | "todo" => Ok(Status::Open),
+            "done" => Ok(Status::Done),
+            _ => Err(format!("unknown status '{}', expected open or done", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Priority::High => "high",
+            Priority::Medium => "medium",
+            Priority::Low => "low",
+        })
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "high" | "🔺" | "⏫" => Ok(Priority::High),
+            "medium" | "🔼" => Ok(Priority::Medium),
+            "low" | "🔽" | "⏬" => Ok(Priority::Low),
+            _ => Err(format!("unknown priority '{}'", s)),
+        }
+    }
+}
+
+/// A checkbox in a note.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Task {
+    /// Short id used by `notes tasks done`
+    pub id: String,
+    /// Name of the note
+    pub name: String,
+    /// File of the task, relative to the vault
+    pub path: PathBuf,
+    /// Line of the task in the file, starting at 1
+    pub line: usize,
+    pub status: Status,
+    /// Text after the checkbox without the due date and priority
+    pub text: String,
+    pub due: Option<NaiveDate>,
+    pub priority: Option<Priority>,
+    /// Tags in the text, without `#`
+    pub tags: Vec<String>,
+    /// Byte offset of the checkbox's mark in the file
+    #[serde(skip)]
+    mark: usize,
+}
+
+/// Which tasks to list. Every criterion that is set must match.
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    pub status: Option<Status>,
+    /// Only tasks due on or before this day
+    pub due_by: Option<NaiveDate>,
+    /// Only tasks with a tag matching this pattern, e.g. `work/*` (see
+    /// [`tag_matches`])
+    pub tag: Option<String>,
+}
+
+impl TaskFilter {
+    pub fn matches(&self, task: &Task) -> bool {
+        if self.status.is_some_and(|status| status != task.status) {
+            return false;
+        }
+        if let Some(day) = self.due_by
+            && task.due.is_none_or(|due| due > day)
+        {
+            return false;
+        }
+        if let Some(pattern) = &self.tag
+            && !task.tags.iter().any(|t| tag_matches(t, pattern))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// A checkbox line: line number in the body, byte offset of the mark and
+/// the text after the checkbox.
+struct Checkbox<'a> {
+    line: usize,
+    mark: usize,
+    status: Status,
+    rest: &'a str,
+}
+
+/// Checkboxes of list items in `body`, outside fenced code blocks.
+fn checkboxes(body: &str) -> Vec<Checkbox<'_>> {
+    let mut out = Vec::new();
+    let mut in_fence = false;
+    let mut offset = 0;
+    for (n, line) in body.split_inclusive('\n').enumerate() {
+        let line_start = offset;
+        offset += line.len();
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let Some(item) = list_item(trimmed) else {
+            continue;
+        };
+        let bytes = item.as_bytes();
+        if bytes.len() < 3 || bytes[0] != b'[' || bytes[2] != b']' {
+            continue;
+        }
+        let status = match bytes[1] {
+            b' ' => Status::Open,
+            b'x' | b'X' => Status::Done,
+            _ => continue,
+        };
+        let rest = &item[3..];
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            continue;
+        }
+        out.push(Checkbox {
+            line: n,
+            mark: line_start + (line.len() - item.len()) + 1,
+            status,
+            rest: rest.trim(),
+        });
+    }
+    out
+}
+
+/// The text of a list item after its marker (`-`, `*`, `+`, `1.` or `1)`).
+fn list_item(line: &str) -> Option<&str> {
+    let rest = match line.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                return None;
+            }
+            line[digits..].strip_prefix(['.', ')'])?
+        }
+    };
+    rest.strip_prefix(' ').map(str::trim_start)
+}
+
+/// Split the text of a task into its words, due date, priority and tags.
+fn parse_text(rest: &str) -> (String, Option<NaiveDate>, Option<Priority>, Vec<String>) {
+    let parse_date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+    let mut words = Vec::new();
+    let mut due = None;
+    let mut priority = None;
+    let mut tags = Vec::new();
+    let mut tokens = rest.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        let date = match token {
+            "📅" | "due:" => tokens.peek().and_then(|next| parse_date(next)),
+            _ => token
+                .strip_prefix("📅")
+                .or_else(|| token.strip_prefix("due:"))
+                .and_then(parse_date),
+        };
+        if date.is_some() {
+            if matches!(token, "📅" | "due:") {
+                tokens.next();
+            }
+            due = date;
+            continue;
+        }
+        let named = token.strip_prefix("priority:").unwrap_or(token);
+        if let Ok(p) = named.parse::<Priority>()
+            && (named != token || !token.is_ascii())
+        {
+            priority = Some(p);
+            continue;
+        }
+        if let Some(tag) = token.strip_prefix('#').and_then(parse_tag) {
+            tags.push(tag.to_string());
+        }
+        words.push(token);
+    }
+    (words.join(" "), due, priority, tags)
+}
+
+fn task_id(path: &Path, text: &str, occurrence: usize) -> String {
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(path.to_string_lossy().as_bytes());
+    hasher.write(&[0]);
+    hasher.write(text.as_bytes());
+    hasher.write(&occurrence.to_le_bytes());
+    format!("{:016x}", hasher.finish())[..7].to_string()
+}
+
+/// Tasks of node `i`, in the order they appear in its files.
+fn node_tasks(data: &GraphData, i: usize) -> Result<Vec<Task>> {
+    let node = &data.graph.nodes[i];
+    let mut out = Vec::new();
+    for path in &node.paths {
+        let bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+        let Ok(text) = String::from_utf8(bytes) else {
+            continue;
+        };
+        let (_, body) = Frontmatter::split(&text);
+        let skipped = text.len() - body.len();
+        let skipped_lines = text[..skipped].matches('\n').count();
+        let rel_path = path.strip_prefix(data.root()).unwrap_or(path).to_path_buf();
+        let first = out.len();
+        for checkbox in checkboxes(body) {
+            let (text, due, priority, tags) = parse_text(checkbox.rest);
+            // identical tasks in one file are told apart by their order
+            let occurrence = out[first..]
+                .iter()
+                .filter(|t: &&Task| t.text == text)
+                .count();
+            out.push(Task {
+                id: task_id(&rel_path, &text, occurrence),
+                name: node.name.clone(),
+                path: rel_path.clone(),
+                line: skipped_lines + checkbox.line + 1,
+                status: checkbox.status,
+                text,
+                due,
+                priority,
+                tags,
+                mark: skipped + checkbox.mark,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Tasks of every note in the vault, sorted by file and line.
+pub fn collect_tasks(data: &GraphData) -> Result<Vec<Task>> {
+    let mut out = Vec::new();
+    for i in 0..data.graph.nodes.len() {
+        out.extend(node_tasks(data, i)?);
+    }
+    out.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    Ok(out)
+}
+
+/// Check the task with this id, changing nothing in its file but the mark
+/// of the checkbox. Returns the task as it was before.
+pub fn complete_task(data: &GraphData, id: &str) -> Result<Task> {
+    let task = collect_tasks(data)?
+        .into_iter()
+        .find(|task| task.id == id)
+        .ok_or_else(|| Error::TaskNotFound(id.to_string()))?;
+    if task.status == Status::Done {
+        return Ok(task);
+    }
+    let path = data.root().join(&task.path);
+    let mut bytes = fs::read(&path).map_err(|e| Error::io(&path, e))?;
+    bytes[task.mark] = b'x';
+    fs::write(&path, bytes).map_err(|e| Error::io(&path, e))?;
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Priority, Status, TaskFilter, checkboxes, collect_tasks, complete_task, parse_text,
+    };
+    use crate::graph::load_from;
+    use crate::testing::TempDir;
+    use chrono::NaiveDate;
+    use std::fs;
+
+    #[test]
+    fn parses_checkboxes_and_metadata() {
+        let body =
+            "- [ ] one\n  * [x] two\n1. [X] three\n- [] no\n- [ ]no\n```\n- [ ] code\n```\n+ [ ]\n";
+        let found: Vec<(usize, Status, &str)> = checkboxes(body)
+            .into_iter()
+            .map(|c| (c.line, c.status, c.rest))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (0, Status::Open, "one"),
+                (1, Status::Done, "two"),
+                (2, Status::Done, "three"),
+                (8, Status::Open, ""),
+            ]
+        );
+        assert_eq!(&body[checkboxes(body)[1].mark..][..1], "x");
+
+        let (text, due, priority, tags) =
+            parse_text("Call Ana ⏫ about #work/ops, 📅 2026-10-20 #1");
+        assert_eq!(text, "Call Ana about #work/ops, #1");
+        assert_eq!(due, NaiveDate::from_ymd_opt(2026, 10, 20));
+        assert_eq!(priority, Some(Priority::High));
+        assert_eq!(tags, ["work/ops"]);
+        let (text, due, priority, _) = parse_text("Pay due:2026-01-02 priority:low high");
+        assert_eq!(text, "Pay high");
+        assert_eq!(due, NaiveDate::from_ymd_opt(2026, 1, 2));
+        assert_eq!(priority, Some(Priority::Low));
+        assert_eq!(parse_text("due: soon").0, "due: soon");
+    }
+
+    #[test]
+    fn collects_filters_and_completes_tasks() {
+        let root = TempDir::new("tasks");
+        let plan = "---\ntags: [x]\n---\n- [ ] Write #work 📅 2026-10-20\r\n- [x] Read\n- [ ] Write #work 📅 2026-10-20\n";
+        fs::write(root.join("Plan.md"), plan).unwrap();
+        fs::write(root.join("Other.md"), "* [ ] Rest #home\n").unwrap();
+
+        let data = load_from(&root).unwrap();
+        let tasks = collect_tasks(&data).unwrap();
+        let lines: Vec<_> = tasks
+            .iter()
+            .map(|t| (t.name.as_str(), t.line, t.text.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                ("Other", 1, "Rest #home"),
+                ("Plan", 4, "Write #work"),
+                ("Plan", 5, "Read"),
+                ("Plan", 6, "Write #work"),
+            ]
+        );
+        assert_ne!(tasks[1].id, tasks[3].id);
+
+        let filter = TaskFilter {
+            status: Some(Status::Open),
+            due_by: NaiveDate::from_ymd_opt(2026, 10, 31),
+            tag: Some("#Work/*".into()),
+        };
+        let open: Vec<_> = tasks.iter().filter(|t| filter.matches(t)).collect();
+        assert_eq!(open.len(), 2);
+        let early = TaskFilter {
+            due_by: NaiveDate::from_ymd_opt(2026, 10, 19),
+            ..TaskFilter::default()
+        };
+        assert!(!tasks.iter().any(|t| early.matches(t)));
+
+        let id = tasks[3].id.clone();
+        complete_task(&data, &id).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("Plan.md")).unwrap(),
+            plan.replacen(
+                "- [ ] Write #work 📅 2026-10-20\n",
+                "- [x] Write #work 📅 2026-10-20\n",
+                1
+            )
+        );
+        let tasks = collect_tasks(&data).unwrap();
+        assert_eq!(tasks[3].id, id);
+        assert_eq!(tasks[3].status, Status::Done);
+        assert!(complete_task(&data, "nothing").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+//! Helpers shared by the unit tests.
+
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// An empty folder in the system's temp folder, for a test to use as a
+/// vault. It is deleted when dropped, so also when the test fails.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create `<name>_test_<pid>`, clearing what an earlier run left there.
+    pub(crate) fn new(name: &str) -> TempDir {
+        let path = env::temp_dir().join(format!("{}_test_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = PathBuf;
+
+    fn deref(&self) -> &PathBuf {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
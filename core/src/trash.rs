@@ -0,0 +1,211 @@
+//! Deleting notes into a trash inside the vault, as used by `notes rm` and
+//! `notes trash`.
+//!
+//! Every deleted note gets its own folder below `.notes/trash/`, holding all
+//! of the note's files under their paths in the vault and an `entry.json`
+//! recording where they came from and when. The entry is written before any
+//! file is moved, so a note that was only partly moved can still be restored.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::graph::GraphData;
+use crate::vault::STATE_DIR;
+
+/// Folder inside [`STATE_DIR`] holding deleted notes.
+const TRASH_DIR: &str = "trash";
+const ENTRY_FILE: &str = "entry.json";
+/// Folder inside an entry holding the note's files.
+const FILES_DIR: &str = "files";
+
+/// A note in the trash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashEntry {
+    /// Name of the entry's folder, used to restore it
+    pub id: String,
+    /// Name of the note
+    pub name: String,
+    /// Path of the note relative to the vault without extension
+    pub rel_path: PathBuf,
+    /// The note's files, relative to the vault
+    pub files: Vec<PathBuf>,
+    pub deleted: DateTime<Local>,
+}
+
+fn trash_dir(root: &Path) -> PathBuf {
+    root.join(STATE_DIR).join(TRASH_DIR)
+}
+
+/// Move every file of node `i` into the trash.
+pub fn trash_node(data: &GraphData, i: usize) -> Result<TrashEntry> {
+    let root = data.root();
+    let node = &data.graph.nodes[i];
+    let deleted = Local::now();
+    let base = deleted.format("%Y%m%d-%H%M%S").to_string();
+    let dir = trash_dir(root);
+    fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+    // several notes deleted within a second each get their own folder
+    let mut id = base.clone();
+    let mut n = 1;
+    while let Err(e) = fs::create_dir(dir.join(&id)) {
+        if e.kind() != std::io::ErrorKind::AlreadyExists {
+            return Err(Error::io(&dir.join(&id), e));
+        }
+        n += 1;
+        id = format!("{}-{}", base, n);
+    }
+    let entry = TrashEntry {
+        id,
+        name: node.name.clone(),
+        rel_path: node.rel_path.clone(),
+        files: node
+            .paths
+            .iter()
+            .map(|path| path.strip_prefix(root).unwrap_or(path).to_path_buf())
+            .collect(),
+        deleted,
+    };
+    let entry_dir = dir.join(&entry.id);
+    let json = serde_json::to_string_pretty(&entry).expect("trash entries serialize");
+    let path = entry_dir.join(ENTRY_FILE);
+    fs::write(&path, json).map_err(|e| Error::io(&path, e))?;
+    for file in &entry.files {
+        move_file(&root.join(file), &entry_dir.join(FILES_DIR).join(file))?;
+    }
+    Ok(entry)
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+    }
+    fs::rename(from, to).map_err(|e| Error::io(from, e))
+}
+
+/// Notes in the trash of the vault at `root`, most recently deleted first.
+/// Folders without a readable entry are skipped.
+pub fn list_trash(root: &Path) -> Result<Vec<TrashEntry>> {
+    let dir = trash_dir(root);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for item in fs::read_dir(&dir).map_err(|e| Error::io(&dir, e))? {
+        let item = item.map_err(|e| Error::io(&dir, e))?;
+        let Ok(text) = fs::read_to_string(item.path().join(ENTRY_FILE)) else {
+            continue;
+        };
+        if let Ok(entry) = serde_json::from_str::<TrashEntry>(&text) {
+            entries.push(entry);
+        }
+    }
+    entries.sort_by(|a, b| b.deleted.cmp(&a.deleted).then_with(|| b.id.cmp(&a.id)));
+    Ok(entries)
+}
+
+/// Find an entry by its id, or else the most recently deleted note with
+/// this name.
+pub fn find_entry(root: &Path, key: &str) -> Result<Option<TrashEntry>> {
+    let entries = list_trash(root)?;
+    let by_id = entries.iter().position(|e| e.id == key);
+    let by_name = || {
+        let wanted = key.to_lowercase();
+        entries
+            .iter()
+            .position(|e| e.name.to_lowercase() == wanted || e.rel_path == Path::new(key))
+    };
+    Ok(by_id.or_else(by_name).map(|k| entries[k].clone()))
+}
+
+/// Move the files of `entry` back to where they were and drop it from the
+/// trash. Nothing is moved if a file has been created in the meantime.
+pub fn restore(root: &Path, entry: &TrashEntry) -> Result<()> {
+    let entry_dir = trash_dir(root).join(&entry.id);
+    let present: Vec<&PathBuf> = entry
+        .files
+        .iter()
+        .filter(|file| entry_dir.join(FILES_DIR).join(file).exists())
+        .collect();
+    if let Some(file) = present.iter().find(|file| root.join(file).exists()) {
+        return Err(Error::NoteExists(root.join(file)));
+    }
+    for file in present {
+        move_file(&entry_dir.join(FILES_DIR).join(file), &root.join(file))?;
+    }
+    fs::remove_dir_all(&entry_dir).map_err(|e| Error::io(&entry_dir, e))
+}
+
+/// Delete everything in the trash for good. Returns the number of notes
+/// deleted.
+pub fn empty_trash(root: &Path) -> Result<usize> {
+    let dir = trash_dir(root);
+    if !dir.is_dir() {
+        return Ok(0);
+    }
+    let count = list_trash(root)?.len();
+    fs::remove_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{empty_trash, find_entry, list_trash, restore, trash_node};
+    use crate::error::Error;
+    use crate::graph::load_from;
+    use crate::testing::TempDir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn trash_and_restore() {
+        let root = TempDir::new("trash");
+        fs::create_dir_all(root.join("projects")).unwrap();
+        fs::write(root.join("projects/Plan.md"), "Text\n").unwrap();
+        fs::write(root.join("projects/Plan.pdf"), "%PDF").unwrap();
+        fs::write(root.join("Other.md"), "See [[Plan]]\n").unwrap();
+
+        let data = load_from(&root).unwrap();
+        let entry = trash_node(&data, data.find("Plan").unwrap()).unwrap();
+        assert_eq!(entry.rel_path, PathBuf::from("projects/Plan"));
+        assert!(!root.join("projects/Plan.md").exists());
+        assert!(!root.join("projects/Plan.pdf").exists());
+        let data = load_from(&root).unwrap();
+        assert_eq!(data.find("Plan"), None);
+
+        let other = trash_node(&data, data.find("Other").unwrap()).unwrap();
+        assert_ne!(other.id, entry.id);
+        let listed: Vec<_> = list_trash(&root)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(listed, ["Other", "Plan"]);
+
+        let found = find_entry(&root, "plan").unwrap().unwrap();
+        assert_eq!(found, entry);
+        assert_eq!(find_entry(&root, &other.id).unwrap().unwrap().name, "Other");
+        assert_eq!(find_entry(&root, "Missing").unwrap(), None);
+
+        fs::write(root.join("projects/Plan.pdf"), "new").unwrap();
+        assert!(matches!(restore(&root, &found), Err(Error::NoteExists(_))));
+        assert!(root.join("projects/Plan.md").metadata().is_err());
+        fs::remove_file(root.join("projects/Plan.pdf")).unwrap();
+        restore(&root, &found).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("projects/Plan.md")).unwrap(),
+            "Text\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("projects/Plan.pdf")).unwrap(),
+            "%PDF"
+        );
+
+        assert_eq!(empty_trash(&root).unwrap(), 1);
+        assert!(list_trash(&root).unwrap().is_empty());
+        assert!(!root.join("Other.md").exists());
+    }
+}
@@ -0,0 +1,348 @@
+//! Finding a vault and walking its files.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{UserConfig, user_config_path};
+use crate::error::{Error, Result};
+use crate::note::NOTES_DIR;
+
+/// Patterns of file and folder names skipped while walking a vault unless
+/// the configuration says otherwise.
+pub const DEFAULT_IGNORE: &[&str] = &[".git", ".obsidian", "target"];
+
+/// Folder inside the vault where notes keeps its own files. It is never
+/// treated as part of the vault's notes.
+pub const STATE_DIR: &str = ".notes";
+
+/// Environment variable naming the vault to use.
+pub const VAULT_ENV: &str = "NOTES_VAULT";
+
+/// How the vault in use was chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VaultSource {
+    /// Given on the command line
+    Flag,
+    /// Named by [`VAULT_ENV`]
+    Env,
+    /// Found by its [`STATE_DIR`] in the current folder or above it
+    Marker,
+    /// Named by the user configuration file at this path
+    UserConfig(PathBuf),
+    /// [`NOTES_DIR`] in the current folder, when nothing else applies
+    Default,
+}
+
+impl fmt::Display for VaultSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VaultSource::Flag => write!(f, "--vault"),
+            VaultSource::Env => write!(f, "{}", VAULT_ENV),
+            VaultSource::Marker => write!(f, "{} folder", STATE_DIR),
+            VaultSource::UserConfig(path) => write!(f, "{}", path.display()),
+            VaultSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultLocation {
+    pub path: PathBuf,
+    pub source: VaultSource,
+}
+
+/// Replace a leading `~` with the home directory.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Decide which vault to use: the one given with `--vault`, else the one in
+/// [`VAULT_ENV`], else the closest folder at or above `cwd` that contains a
+/// [`STATE_DIR`], else the one named in the user configuration file, else
+/// [`NOTES_DIR`] below `cwd`. The vault is not required to exist.
+pub fn find_vault(flag: Option<&Path>, cwd: &Path) -> Result<VaultLocation> {
+    let env_vault = env::var_os(VAULT_ENV)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from);
+    locate_vault(flag, env_vault, cwd, user_config_path().as_deref())
+}
+
+fn locate_vault(
+    flag: Option<&Path>,
+    env_vault: Option<PathBuf>,
+    cwd: &Path,
+    user_config: Option<&Path>,
+) -> Result<VaultLocation> {
+    let found = |path: PathBuf, source| Ok(VaultLocation { path, source });
+    if let Some(path) = flag {
+        return found(expand_home(path), VaultSource::Flag);
+    }
+    if let Some(path) = env_vault {
+        return found(expand_home(&path), VaultSource::Env);
+    }
+    if let Some(dir) = cwd.ancestors().find(|dir| dir.join(STATE_DIR).is_dir()) {
+        return found(dir.to_path_buf(), VaultSource::Marker);
+    }
+    if let Some(config_path) = user_config {
+        let config = UserConfig::load_from(config_path)?;
+        if let Some(path) = config.vault {
+            return found(
+                expand_home(&path),
+                VaultSource::UserConfig(config_path.to_path_buf()),
+            );
+        }
+    }
+    found(PathBuf::from(NOTES_DIR), VaultSource::Default)
+}
+
+/// Whether `name` matches the glob `pattern`, where `*` stands for any run
+/// of characters and `?` for a single one.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` and of the name when it was reached
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // let the last `*` swallow one more character
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    n = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether a file or folder called `name` matches one of the `ignore`
+/// patterns.
+pub fn is_ignored(name: &str, ignore: &[String]) -> bool {
+    ignore.iter().any(|pattern| glob_match(pattern, name))
+}
+
+/// The files found by [`walk`].
+#[derive(Debug, Default)]
+pub struct Walk {
+    /// Every file found, sorted by path
+    pub files: Vec<PathBuf>,
+    /// Folders and entries below the root that could not be read, with the
+    /// reason. Nothing below them is in `files`.
+    pub skipped: Vec<(PathBuf, Error)>,
+}
+
+impl Walk {
+    /// Whether `path` lies in a part of the tree that could not be read.
+    pub fn is_skipped(&self, path: &Path) -> bool {
+        self.skipped.iter().any(|(p, _)| path.starts_with(p))
+    }
+}
+
+/// Recursively list every file below `root`.
+///
+/// Entries whose file name matches a pattern in `ignore` are skipped, and so
+/// is everything below an ignored folder or the [`STATE_DIR`] at the top of
+/// the vault. Symlinked folders are not followed so that a link back up the
+/// tree cannot make the walk loop forever. A folder below `root` that cannot
+/// be read is left out and listed in [`Walk::skipped`]; only an unreadable
+/// `root` is an error.
+pub fn walk(root: &Path, ignore: &[String]) -> Result<Walk> {
+    if !root.is_dir() {
+        return Err(Error::VaultNotFound(root.to_path_buf()));
+    }
+    let mut out = Walk::default();
+    let state_dir = root.join(STATE_DIR);
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if dir == root => return Err(Error::io(&dir, e)),
+            Err(e) => {
+                let error = Error::io(&dir, e);
+                out.skipped.push((dir, error));
+                continue;
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    out.skipped.push((dir.clone(), Error::io(&dir, e)));
+                    break;
+                }
+            };
+            let name = entry.file_name();
+            if is_ignored(&name.to_string_lossy(), ignore) {
+                continue;
+            }
+            let path = entry.path();
+            if path == state_dir {
+                continue;
+            }
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(e) => {
+                    let error = Error::io(&path, e);
+                    out.skipped.push((path, error));
+                    continue;
+                }
+            };
+            if file_type.is_dir() {
+                stack.push(path);
+            } else if file_type.is_file() || path.is_file() {
+                out.files.push(path);
+            }
+        }
+    }
+    out.files.sort();
+    out.skipped.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_IGNORE, VaultSource, glob_match, locate_vault, walk};
+    use crate::error::Error;
+    use crate::testing::TempDir;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn walks_subfolders_and_skips_ignored() {
+        let root = TempDir::new("vault_walk");
+        for dir in ["projects/2024", ".git/objects", "target", ".notes"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "Index.md",
+            "projects/Plan.md",
+            "projects/2024/Plan.md",
+            ".git/HEAD",
+            ".git/objects/ab",
+            "target/out.md",
+            ".notes/index",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+
+        let ignore: Vec<String> = DEFAULT_IGNORE.iter().map(|s| s.to_string()).collect();
+        let walked = walk(&root, &ignore).unwrap();
+        assert!(walked.skipped.is_empty());
+        let files: Vec<_> = walked
+            .files
+            .into_iter()
+            .map(|p| p.strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        let expected: Vec<std::path::PathBuf> =
+            ["Index.md", "projects/2024/Plan.md", "projects/Plan.md"]
+                .iter()
+                .map(Into::into)
+                .collect();
+        assert_eq!(files, expected);
+
+        // an unreadable folder is skipped, not fatal; root reads every folder
+        // regardless of permissions, so this only runs as another user
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let locked = root.join("projects/2024");
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+            if fs::read_dir(&locked).is_err() {
+                let walked = walk(&root, &ignore).unwrap();
+                assert_eq!(walked.skipped.len(), 1);
+                assert!(walked.is_skipped(&locked.join("Plan.md")));
+                assert_eq!(
+                    walked.files,
+                    [root.join("Index.md"), root.join("projects/Plan.md")]
+                );
+            }
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let _ = fs::remove_dir_all(&root);
+        assert!(matches!(
+            walk(&root, &ignore),
+            Err(Error::VaultNotFound(path)) if path == *root
+        ));
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("target", "target"));
+        assert!(!glob_match("target", "targets"));
+        assert!(glob_match("*.tmp", "draft.tmp"));
+        assert!(glob_match("*.tmp", ".tmp"));
+        assert!(!glob_match("*.tmp", "draft.tmp.md"));
+        assert!(glob_match("draft?", "draft1"));
+        assert!(!glob_match("draft?", "draft"));
+        assert!(glob_match("a*b*c", "axxbyybc"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn vault_lookup_order() {
+        let root = TempDir::new("vault_find");
+        fs::create_dir_all(root.join("marked/.notes")).unwrap();
+        fs::create_dir_all(root.join("marked/deep/er")).unwrap();
+        fs::create_dir_all(root.join("plain")).unwrap();
+        let config = root.join("config.toml");
+        fs::write(&config, "vault = \"/srv/vault\"\n").unwrap();
+        let deep = root.join("marked/deep/er");
+        let plain = root.join("plain");
+
+        let pick = |flag: Option<&str>, env: Option<&str>, cwd: &Path| {
+            let found = locate_vault(
+                flag.map(Path::new),
+                env.map(PathBuf::from),
+                cwd,
+                Some(&config),
+            );
+            let found = found.unwrap();
+            (found.path, found.source)
+        };
+        assert_eq!(
+            pick(Some("a"), Some("b"), &deep),
+            (PathBuf::from("a"), VaultSource::Flag)
+        );
+        assert_eq!(
+            pick(None, Some("b"), &deep),
+            (PathBuf::from("b"), VaultSource::Env)
+        );
+        assert_eq!(
+            pick(None, None, &deep),
+            (root.join("marked"), VaultSource::Marker)
+        );
+        assert_eq!(
+            pick(None, None, &plain),
+            (
+                PathBuf::from("/srv/vault"),
+                VaultSource::UserConfig(config.clone())
+            )
+        );
+        let found = locate_vault(None, None, &plain, None).unwrap();
+        assert_eq!(found.path, PathBuf::from("notes"));
+        assert_eq!(found.source, VaultSource::Default);
+
+        fs::write(&config, "vault = [").unwrap();
+        assert!(matches!(
+            locate_vault(None, None, &plain, Some(&config)),
+            Err(Error::Config { .. })
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+//! Watching a vault for changes made outside of notes.
+//!
+//! A [`VaultWatcher`] listens for file system events below the vault (inotify
+//! on Linux) and groups them into batches: after the first event it waits
+//! until the vault has been quiet for a moment, so that a git pull or an
+//! editor's save-by-rename arrives as one batch instead of dozens. Every
+//! subscriber receives each batch as the list of paths that were created,
+//! modified, renamed or deleted, ready to be passed to
+//! [`update_paths`](crate::graph::update_paths).
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::{Error, Result};
+use crate::vault::STATE_DIR;
+
+/// How long the vault has to be quiet before a batch is sent.
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+type Subscribers = Arc<Mutex<Vec<Sender<Vec<PathBuf>>>>>;
+
+/// Watches a vault until dropped.
+pub struct VaultWatcher {
+    _watcher: RecommendedWatcher,
+    subscribers: Subscribers,
+}
+
+impl VaultWatcher {
+    /// Start watching the vault at `root` and everything below it.
+    pub fn new(root: &Path) -> Result<Self> {
+        if !root.is_dir() {
+            return Err(Error::VaultNotFound(root.to_path_buf()));
+        }
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).map_err(|e| Error::watch(root, e))?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| Error::watch(root, e))?;
+
+        let subscribers: Subscribers = Arc::default();
+        let state_dir = root.join(STATE_DIR);
+        let absolute_state_dir = std::path::absolute(&state_dir).unwrap_or_default();
+        let thread_subscribers = subscribers.clone();
+        thread::spawn(move || {
+            // ends once the watcher is dropped and the channel closes
+            while let Ok(first) = rx.recv() {
+                let mut batch = BTreeSet::new();
+                let mut next = Some(first);
+                while let Some(result) = next {
+                    // errors are transient, e.g. an event queue overflow; the
+                    // next refresh of the graph catches anything missed
+                    if let Ok(event) = result
+                        && !matches!(event.kind, EventKind::Access(_))
+                    {
+                        batch.extend(event.paths.into_iter().filter(|p| {
+                            !p.starts_with(&state_dir) && !p.starts_with(&absolute_state_dir)
+                        }));
+                    }
+                    next = match rx.recv_timeout(SETTLE_TIME) {
+                        Ok(result) => Some(result),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    };
+                }
+                if batch.is_empty() {
+                    continue;
+                }
+                let batch: Vec<PathBuf> = batch.into_iter().collect();
+                let mut subscribers = thread_subscribers.lock().unwrap();
+                subscribers.retain(|s| s.send(batch.clone()).is_ok());
+            }
+        });
+
+        Ok(VaultWatcher {
+            _watcher: watcher,
+            subscribers,
+        })
+    }
+
+    /// Receive every batch of changed paths from now on. The receiver can be
+    /// polled from a UI loop with `try_recv` or blocked on with `recv`.
+    pub fn subscribe(&self) -> Receiver<Vec<PathBuf>> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VaultWatcher;
+    use crate::testing::TempDir;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn batches_changes_and_skips_state_dir() {
+        let root = TempDir::new("vault_watch");
+        fs::create_dir_all(root.join(".notes")).unwrap();
+
+        let watcher = VaultWatcher::new(&root).unwrap();
+        let changes = watcher.subscribe();
+        fs::write(root.join(".notes/index"), "{}").unwrap();
+        fs::write(root.join("A.md"), "one").unwrap();
+        fs::write(root.join("B.md"), "two").unwrap();
+        fs::remove_file(root.join("A.md")).unwrap();
+
+        let batch = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+        let names: Vec<_> = batch.iter().map(|p| p.file_name().unwrap()).collect();
+        assert_eq!(names, ["A.md", "B.md"]);
+        drop(watcher);
+    }
+}
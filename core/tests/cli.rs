@@ -1,32 +1,770 @@
-use std::env;
+mod common;
+
+use common::TempDir;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::Path;
+use std::process::{Command, Stdio};
 
-fn temp_dir() -> PathBuf {
-    let mut dir = env::temp_dir();
-    dir.push(format!("cli_test_{}", std::process::id()));
-    let _ = fs::remove_dir_all(&dir);
-    fs::create_dir_all(&dir).unwrap();
-    dir
+/// The `notes` binary run in `dir`, isolated from the developer's own
+/// vault: `NOTES_VAULT` is unset and the user config is read from
+/// `dir/config`.
+fn notes(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_notes"));
+    command
+        .current_dir(dir)
+        .env_remove("NOTES_VAULT")
+        .env("XDG_CONFIG_HOME", dir.join("config"));
+    command
 }
 
 #[test]
 fn cli_new_and_show() {
-    let dir = temp_dir();
-    env::set_current_dir(&dir).unwrap();
-    let exe = env!("CARGO_BIN_EXE_notes");
+    let dir = TempDir::new("cli");
+
+    let output = notes(&dir).args(["new", "MyCliNote"]).output().unwrap();
+    assert!(output.status.success());
+
+    let output = notes(&dir).args(["show", "MyCliNote"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--- MyCliNote ---"));
+}
 
-    let output = Command::new(exe)
-        .args(["new", "MyCliNote"])
+#[test]
+fn cli_list() {
+    let dir = TempDir::new("cli_list");
+    fs::create_dir_all(dir.join("notes/work")).unwrap();
+    fs::write(
+        dir.join("notes/work/Plan.md"),
+        "---\ntags: [project]\n---\nsee Ideas\n",
+    )
+    .unwrap();
+    fs::write(dir.join("notes/work/Plan.pdf"), [0xff, 0xfe]).unwrap();
+    fs::write(dir.join("notes/Ideas.md"), "").unwrap();
+    let list = |args: &[&str]| {
+        let output = notes(&dir).arg("list").args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let tsv = list(&["--format", "tsv", "--sort", "links"]);
+    let lines: Vec<&str> = tsv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "name\tpath\tformats\tmodified\tlinks\ttags\taliases"
+    );
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("Ideas\tIdeas\tmd\t"));
+    assert!(lines[2].starts_with("Plan\twork/Plan\tmd,pdf\t"));
+    assert!(lines[2].ends_with("\t1\tproject\t"));
+
+    let json = list(&["--json", "--tag", "project"]);
+    assert!(json.contains("\"path\": \"work/Plan\""));
+    assert!(!json.contains("Ideas"));
+    assert_eq!(list(&["--type", "pdf"]).lines().count(), 1);
+    assert_eq!(list(&["--folder", "work"]).lines().count(), 1);
+
+    let output = notes(&dir)
+        .args(["list", "--sort", "size"])
         .output()
         .unwrap();
+    assert!(!output.status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn cli_edit() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new("cli_edit");
+    fs::create_dir_all(dir.join("notes/.notes/templates")).unwrap();
+    fs::create_dir_all(dir.join("notes/work")).unwrap();
+    fs::write(dir.join("notes/work/Road Map.md"), "plans").unwrap();
+    fs::write(
+        dir.join("notes/.notes/templates/default.md"),
+        "---\ntags: [inbox]\n---\n# {{title}}\n",
+    )
+    .unwrap();
+    // an "editor" that records which file it was asked to open
+    let editor = dir.join("editor.sh");
+    fs::write(&editor, "#!/bin/sh\necho \"$1\" >> edited.log\n").unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+    let edit = |title: &str| {
+        let output = notes(&dir)
+            .env_remove("VISUAL")
+            .env("EDITOR", &editor)
+            .args(["edit", title])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+    };
+
+    edit("road-map");
+    edit("New Idea");
+    let log = fs::read_to_string(dir.join("edited.log")).unwrap();
+    assert_eq!(log, "notes/work/Road Map.md\nnotes/New Idea.md\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("notes/New Idea.md")).unwrap(),
+        "---\ntags: [inbox]\n---\n# New Idea\n"
+    );
+}
+
+#[test]
+fn cli_options_exit_codes_and_completion() {
+    let dir = TempDir::new("cli_options");
+    fs::create_dir_all(dir.join("vault")).unwrap();
+    fs::write(dir.join("vault/Road Map.md"), "---\naliases: [Plan]\n---\n").unwrap();
+    let run = |args: &[&str]| notes(&dir).args(args).output().unwrap();
+
+    let output = run(&["--vault", "vault", "--quiet", "new", "Second", "Note"]);
     assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(dir.join("vault/Second Note.md").is_file());
+
+    let output = run(&["--vault", "vault", "show", "Missing"]);
+    assert_eq!(output.status.code(), Some(66));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Note 'Missing' not found.\n"
+    );
+    assert_eq!(
+        run(&["--vault", "vault", "new", ". ."]).status.code(),
+        Some(65)
+    );
+    assert_eq!(run(&["--vault", "nowhere", "list"]).status.code(), Some(66));
+    assert_eq!(run(&["list", "--bogus"]).status.code(), Some(64));
+    let output = run(&["list", "--help"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--sort <KEY>"));
+
+    fs::write(dir.join("bad.toml"), "link_mode = 3").unwrap();
+    assert_eq!(
+        run(&["--config", "bad.toml", "list"]).status.code(),
+        Some(78)
+    );
+
+    let output = run(&["completions", "bash"]);
+    assert!(output.status.success());
+    assert!(!output.stdout.is_empty());
+    let output = notes(&dir)
+        .env("COMPLETE", "fish")
+        .args(["--", "notes", "show", ""])
+        .output()
+        .unwrap();
+    let candidates = String::from_utf8_lossy(&output.stdout);
+    assert!(!candidates.contains("Road Map"));
+    let vault = dir.join("vault").display().to_string();
+    let vault_eq = format!("--vault={}", vault);
+    for args in [vec!["--vault", &vault], vec![&vault_eq]] {
+        let output = notes(&dir)
+            .env("COMPLETE", "fish")
+            .args(["--", "notes"])
+            .args(&args)
+            .args(["show", ""])
+            .output()
+            .unwrap();
+        let candidates = String::from_utf8_lossy(&output.stdout);
+        assert!(candidates.contains("Road Map"), "{:?}", args);
+    }
+    fs::rename(dir.join("vault"), dir.join("notes")).unwrap();
+    let output = notes(&dir)
+        .env("COMPLETE", "fish")
+        .args(["--", "notes", "edit", "R"])
+        .output()
+        .unwrap();
+    let candidates = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(candidates.lines().collect::<Vec<_>>(), ["Road Map"]);
+}
+
+#[test]
+fn cli_vault_discovery() {
+    let dir = TempDir::new("cli_vault");
+    fs::create_dir_all(dir.join("marked/.notes")).unwrap();
+    fs::create_dir_all(dir.join("marked/sub")).unwrap();
+    fs::create_dir_all(dir.join("from-env")).unwrap();
+    fs::create_dir_all(dir.join("plain")).unwrap();
+    fs::create_dir_all(dir.join("config/notes")).unwrap();
+    fs::write(
+        dir.join("config/notes/config.toml"),
+        format!("vault = {:?}\n", dir.join("configured")),
+    )
+    .unwrap();
+    let run = |cwd: &str, env_vault: Option<&str>, args: &[&str]| {
+        let mut command = notes(&dir);
+        command.current_dir(dir.join(cwd)).args(args);
+        if let Some(vault) = env_vault {
+            command.env("NOTES_VAULT", dir.join(vault));
+        }
+        let output = command.output().unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert_eq!(
+        run("marked/sub", None, &["vault"]),
+        format!("{} (from .notes folder)\n", dir.join("marked").display())
+    );
+    assert_eq!(
+        run("marked/sub", Some("from-env"), &["vault"]),
+        format!("{} (from NOTES_VAULT)\n", dir.join("from-env").display())
+    );
+    assert_eq!(
+        run(
+            "marked/sub",
+            Some("from-env"),
+            &["--vault", "x", "-q", "vault"]
+        ),
+        "x\n"
+    );
+    assert_eq!(
+        run("plain", None, &["-q", "vault"]),
+        format!("{}\n", dir.join("configured").display())
+    );
 
-    let output = Command::new(exe)
-        .args(["show", "MyCliNote"])
+    run("marked/sub", None, &["new", "Found"]);
+    assert!(dir.join("marked/Found.md").is_file());
+    assert!(run("marked", None, &["list"]).contains("Found"));
+
+    let output = notes(&dir)
+        .current_dir(dir.join("plain"))
+        .arg("list")
         .output()
         .unwrap();
+    assert_eq!(output.status.code(), Some(66));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("(from "));
+}
+
+#[test]
+fn cli_vault_config() {
+    let dir = TempDir::new("cli_config");
+    fs::create_dir_all(dir.join(".notes")).unwrap();
+    fs::create_dir_all(dir.join("drafts")).unwrap();
+    fs::write(
+        dir.join(".notes/config.toml"),
+        "version = 1\ndefault_format = \"txt\"\nignore = [\"draft*\"]\nspeling = true\n",
+    )
+    .unwrap();
+    fs::write(dir.join("drafts/Hidden.md"), "").unwrap();
+    let run = |args: &[&str]| notes(&dir).args(args).output().unwrap();
+
+    let output = run(&["new", "Plain"]);
+    assert!(output.status.success());
+    assert!(dir.join("Plain.txt").is_file());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Warning: unknown key 'speling'\n"
+    );
+    assert!(run(&["-q", "new", "Quiet"]).stderr.is_empty());
+    let output = run(&["-q", "list", "--format", "tsv"]);
     let stdout = String::from_utf8_lossy(&output.stdout);
-    assert!(stdout.contains("--- MyCliNote ---"));
+    assert!(stdout.contains("Plain"));
+    assert!(!stdout.contains("Hidden"));
+
+    fs::write(dir.join(".notes/config.toml"), "version = 9\n").unwrap();
+    let output = run(&["list"]);
+    assert_eq!(output.status.code(), Some(78));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("version 9"));
+}
+
+#[test]
+fn cli_search() {
+    let dir = TempDir::new("cli_search");
+    fs::create_dir_all(dir.join("notes/work")).unwrap();
+    fs::write(
+        dir.join("notes/work/Road Map.md"),
+        "---\ntags: [work]\n---\nShip the road map.\nThen rest.\n",
+    )
+    .unwrap();
+    fs::write(dir.join("notes/Trip.md"), "A map of the road ahead.\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = notes(&dir).args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert_eq!(
+        run(&["search", "road map"]),
+        "work/Road Map\n  work/Road Map.md:4: Ship the road map.\n"
+    );
+    assert_eq!(
+        run(&["search", "--color", "always", "road", "map", "tag:work"]),
+        "work/Road Map\n  work/Road Map.md:4: Ship the \x1b[1;31mroad\x1b[0m \x1b[1;31mmap\x1b[0m.\n"
+    );
+    let output = run(&["search", "map", "--json"]);
+    let hits: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(hits.as_array().unwrap().len(), 2);
+    assert_eq!(run(&["search", "nothing"]), "No notes match.\n");
+}
+
+#[test]
+fn cli_links_and_backlinks() {
+    let dir = TempDir::new("cli_links");
+    fs::create_dir_all(dir.join("notes")).unwrap();
+    fs::write(dir.join("notes/Power.md"), "Just power.\n").unwrap();
+    fs::write(
+        dir.join("notes/Nuclear Power.md"),
+        "---\naliases: [Fission]\n---\nA kind of [[Power]].\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("notes/Plan.md"),
+        "Build nuclear power.\nFission too.\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let output = notes(&dir).args(args).output().unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+
+    assert_eq!(
+        run(&["links", "Plan"]).1,
+        "Nuclear Power  implicit\n  Plan.md:1: Build nuclear power.\n  Plan.md:2: Fission too.\n"
+    );
+    assert_eq!(
+        run(&["backlinks", "Power"]).1,
+        "Nuclear Power  explicit\n  Nuclear Power.md:4: A kind of [[Power]].\n\n\
+         Unlinked mentions:\nPlan  unlinked\n  Plan.md:1: Build nuclear power.\n"
+    );
+    let (_, output) = run(&["backlinks", "Fission", "--json"]);
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["backlinks"][0]["name"], "Plan");
+    assert_eq!(json["backlinks"][0]["kind"], "implicit");
+    assert_eq!(json["unlinked"].as_array().unwrap().len(), 0);
+    assert_eq!(run(&["links", "Missing"]).0, Some(66));
+
+    // The link mode is read from the vault's own config.
+    fs::create_dir_all(dir.join("notes/.notes")).unwrap();
+    fs::write(
+        dir.join("notes/.notes/config.toml"),
+        "link_mode = \"explicit\"\n",
+    )
+    .unwrap();
+    assert_eq!(run(&["links", "Plan"]).1, "'Plan' links to no notes.\n");
+    assert!(
+        run(&["backlinks", "Power"])
+            .1
+            .starts_with("Nuclear Power  explicit\n")
+    );
+}
+
+#[test]
+fn cli_mv() {
+    let dir = TempDir::new("cli_mv");
+    fs::create_dir_all(dir.join("notes")).unwrap();
+    fs::write(dir.join("notes/Power.md"), "Just power.\n").unwrap();
+    fs::write(dir.join("notes/Plan.md"), "Use [[Power]].\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = notes(&dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+
+    let (code, output) = run(&["mv", "Power", "Energy", "--dry-run"]);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        output,
+        "rename Power.md -> Energy.md\n--- Plan.md\n+++ Plan.md\n@@ -1 +1 @@\n\
+         -Use [[Power]].\n+Use [[Energy]].\n"
+    );
+    assert!(dir.join("notes/Power.md").exists());
+    // without a terminal to confirm on, --yes is required
+    assert_eq!(run(&["mv", "Power", "Energy"]).0, Some(64));
+    assert_eq!(run(&["mv", "Power", "Plan", "-y"]).0, Some(73));
+
+    assert_eq!(
+        run(&["-q", "mv", "Power", "Energy", "--yes"]),
+        (Some(0), String::new())
+    );
+    assert!(!dir.join("notes/Power.md").exists());
+    assert_eq!(
+        fs::read_to_string(dir.join("notes/Energy.md")).unwrap(),
+        "Just power.\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("notes/Plan.md")).unwrap(),
+        "Use [[Energy]].\n"
+    );
+    assert_eq!(
+        run(&["links", "Plan"]).1,
+        "Energy  explicit\n  Plan.md:1: Use [[Energy]].\n"
+    );
+}
+
+#[test]
+fn cli_rm_and_trash() {
+    let dir = TempDir::new("cli_rm");
+    fs::create_dir_all(dir.join("notes")).unwrap();
+    fs::write(dir.join("notes/Power.md"), "Just power.\n").unwrap();
+    fs::write(dir.join("notes/Power.pdf"), "%PDF").unwrap();
+    fs::write(dir.join("notes/Plan.md"), "Use [[Power]].\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = notes(&dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+    };
+
+    // breaks a link, so it needs confirming
+    let (code, _, stderr) = run(&["rm", "Power"]);
+    assert_eq!(code, Some(64));
+    assert!(stderr.contains("lose the link: Plan"), "{}", stderr);
+    assert!(dir.join("notes/Power.pdf").exists());
+    let (code, stdout, _) = run(&["rm", "Power", "--yes"]);
+    assert_eq!(code, Some(0));
+    assert!(
+        stdout.starts_with("Moved 'Power' to the trash"),
+        "{}",
+        stdout
+    );
+    assert!(!dir.join("notes/Power.md").exists());
+    assert!(!dir.join("notes/Power.pdf").exists());
+    assert_eq!(run(&["show", "Power"]).0, Some(66));
+
+    let (_, listed, _) = run(&["trash", "list"]);
+    assert!(
+        listed.trim_end().ends_with("Power  (2 files)"),
+        "{}",
+        listed
+    );
+    assert_eq!(run(&["trash", "restore", "Missing"]).0, Some(66));
+    assert_eq!(run(&["trash", "restore", "power"]).0, Some(0));
+    assert_eq!(
+        fs::read_to_string(dir.join("notes/Power.md")).unwrap(),
+        "Just power.\n"
+    );
+    assert_eq!(run(&["trash", "list"]).1, "The trash is empty.\n");
+
+    // nothing links to it
+    assert_eq!(run(&["rm", "Plan"]).0, Some(0));
+    assert_eq!(run(&["trash", "empty"]).0, Some(64));
+    assert_eq!(
+        run(&["trash", "empty", "-y"]).1,
+        "Deleted 1 note for good.\n"
+    );
+    assert!(!dir.join("notes/Plan.md").exists());
+}
+
+#[test]
+fn cli_new_from_template() {
+    let dir = TempDir::new("cli_template");
+    fs::create_dir_all(dir.join("notes/.notes/templates")).unwrap();
+    fs::write(
+        dir.join("notes/.notes/templates/meeting.md"),
+        "---\ntags: [meeting]\n---\n# {{title}}\nWith {{who}}, {{topic}}.\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("notes/.notes/templates/default.md"),
+        "Created {{date:%Y}}\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        notes(&dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+
+    let args = [
+        "new",
+        "Standup",
+        "--template",
+        "meeting",
+        "--var",
+        "who=Ana",
+    ];
+    assert_eq!(run(&args), Some(0));
+    assert_eq!(
+        fs::read_to_string(dir.join("notes/Standup.md")).unwrap(),
+        "---\ntags: [meeting]\n---\n# Standup\nWith Ana, .\n"
+    );
+    assert_eq!(run(&["new", "Other"]), Some(0));
+    let text = fs::read_to_string(dir.join("notes/Other.md")).unwrap();
+    assert!(text.starts_with("Created 20"), "{}", text);
+    assert_eq!(run(&["new", "X", "-t", "missing"]), Some(66));
+    assert_eq!(run(&["new", "X", "--var", "novalue"]), Some(64));
+    assert!(!dir.join("notes/X.md").exists());
+
+    // an existing note is never overwritten
+    fs::write(dir.join("notes/Plan.md"), "keep me").unwrap();
+    assert_eq!(run(&["new", "Plan"]), Some(73));
+    assert_eq!(run(&["new", "Plan", "-t", "meeting"]), Some(73));
+    assert_eq!(
+        fs::read_to_string(dir.join("notes/Plan.md")).unwrap(),
+        "keep me"
+    );
+}
+
+#[test]
+fn cli_periodic_notes() {
+    let dir = TempDir::new("cli_periodic");
+    fs::create_dir_all(dir.join("notes/.notes/templates")).unwrap();
+    fs::write(
+        dir.join("notes/.notes/templates/daily.md"),
+        "# {{title}}\nA {{date:%A}}.\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| notes(&dir).args(args).output().unwrap();
+
+    let output = run(&[
+        "--quiet",
+        "daily",
+        "--date",
+        "2026-10-18",
+        "--offset",
+        "-1",
+        "--no-edit",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "notes/journal/2026-10-17.md\n"
+    );
+    let path = dir.join("notes/journal/2026-10-17.md");
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "# 2026-10-17\nA Saturday.\n"
+    );
+    // an existing note is opened as it is
+    fs::write(&path, "Edited\n").unwrap();
+    assert!(
+        run(&["daily", "--date", "2026-10-17", "--no-edit"])
+            .status
+            .success()
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "Edited\n");
+
+    let output = run(&["weekly", "--date", "2026-10-18", "--no-edit"]);
+    assert!(output.status.success());
+    assert!(dir.join("notes/journal/2026-W42.md").is_file());
+    let output = run(&[
+        "monthly",
+        "--date",
+        "2026-10-18",
+        "--offset",
+        "2",
+        "--no-edit",
+    ]);
+    assert!(output.status.success());
+    assert!(dir.join("notes/journal/2026-12.md").is_file());
+    assert!(run(&["today", "--no-edit"]).status.success());
+    assert_eq!(
+        run(&["daily", "--date", "18.10.2026"]).status.code(),
+        Some(64)
+    );
+}
+
+#[test]
+fn cli_tasks() {
+    let dir = TempDir::new("cli_tasks");
+    fs::create_dir_all(dir.join("notes")).unwrap();
+    let plan = "# Plan\n- [ ] Call Ana #work 📅 2026-10-20 ⏫\n- [x] Book room #work\n";
+    fs::write(dir.join("notes/Plan.md"), plan).unwrap();
+    fs::write(dir.join("notes/Home.md"), "- [ ] Water plants #home\n").unwrap();
+    let run = |args: &[&str]| notes(&dir).args(args).output().unwrap();
+
+    let output = run(&["tasks", "--status", "open", "--tag", "work", "--json"]);
+    assert!(output.status.success());
+    let tasks: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let tasks = tasks.as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["text"], "Call Ana #work");
+    assert_eq!(tasks[0]["due"], "2026-10-20");
+    assert_eq!(tasks[0]["priority"], "high");
+    assert_eq!(tasks[0]["line"], 2);
+    let id = tasks[0]["id"].as_str().unwrap().to_string();
+
+    let output = run(&["tasks", "--due", "2026-10-31"]);
+    let listed = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(listed.lines().count(), 1, "{}", listed);
+    assert!(
+        listed.contains(&format!("{}  [ ] Call Ana #work", id)),
+        "{}",
+        listed
+    );
+    assert_eq!(run(&["tasks", "--due", "someday"]).status.code(), Some(64));
+
+    assert!(run(&["tasks", "done", &id]).status.success());
+    assert_eq!(
+        fs::read_to_string(dir.join("notes/Plan.md")).unwrap(),
+        plan.replacen("- [ ]", "- [x]", 1)
+    );
+    let output = run(&["tasks", "--status", "open"]);
+    let listed = String::from_utf8_lossy(&output.stdout);
+    assert!(listed.contains("Water plants"), "{}", listed);
+    assert!(!listed.contains("Call Ana"), "{}", listed);
+    assert_eq!(run(&["tasks", "done", "0000000"]).status.code(), Some(66));
+}
+
+#[test]
+fn cli_tags() {
+    let dir = TempDir::new("cli_tags");
+    fs::create_dir_all(dir.join("notes")).unwrap();
+    fs::write(
+        dir.join("notes/Plan.md"),
+        "---\ntags: [area/ops]\n---\nAbout #area/dev and #misc.\n",
+    )
+    .unwrap();
+    fs::write(dir.join("notes/Budget.md"), "Money #area\n").unwrap();
+    fs::write(dir.join("notes/Ideas.md"), "# Ideas\n`#code`\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = notes(&dir).args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(
+        run(&["tags"]),
+        "#area  2 notes\n  #dev  1 note\n  #ops  1 note\n#misc  1 note\n"
+    );
+    let tree: serde_json::Value = serde_json::from_str(&run(&["tags", "--json"])).unwrap();
+    assert_eq!(tree[0]["children"][1]["tag"], "area/ops");
+
+    let names = |args: &[&str]| -> Vec<String> {
+        let mut all = vec!["list", "--format", "tsv"];
+        all.extend(args);
+        run(&all)
+            .lines()
+            .skip(1)
+            .map(|line| line.split('\t').next().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(names(&["--tag", "area/*"]), ["Budget", "Plan"]);
+    assert_eq!(names(&["--tag", "area"]), ["Budget"]);
+    assert_eq!(names(&["--tag", "#area/dev"]), ["Plan"]);
+    assert!(names(&["--tag", "code"]).is_empty());
+}
+
+#[test]
+fn cli_show_section_and_check() {
+    let dir = TempDir::new("cli_anchors");
+    fs::create_dir_all(dir.join("notes")).unwrap();
+    fs::write(
+        dir.join("notes/Guide.md"),
+        "# Guide\nIntro.\n\n## Setup\nInstall it. ^install\n\n## Usage\nRun it.\n",
+    )
+    .unwrap();
+    fs::write(dir.join("notes/Plan.md"), "See [[Guide#Setup]].\n").unwrap();
+    let run = |args: &[&str]| notes(&dir).args(args).output().unwrap();
+
+    let output = run(&["show", "Guide#setup"]);
+    assert!(output.status.success());
+    let shown = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        shown.starts_with("--- Guide#setup ---\n## Setup\nInstall it. ^install\n\n(Source: "),
+        "{}",
+        shown
+    );
+    assert!(shown.ends_with(", lines 4-5)\n"), "{}", shown);
+    let output = run(&["show", "Guide#^install"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\nInstall it. ^install\n"));
+    assert_eq!(run(&["show", "Guide#Missing"]).status.code(), Some(66));
+
+    assert!(run(&["check"]).status.success());
+    fs::write(
+        dir.join("notes/Plan.md"),
+        "See [[Guide#Setup]].\nAnd [[Guide#Install]].\n",
+    )
+    .unwrap();
+    let output = run(&["check"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Plan.md:2: 'Guide' has no heading or block 'Install'\n"
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("1 link points to a missing heading or block.")
+    );
+}
+
+#[test]
+fn cli_render_and_export() {
+    let dir = TempDir::new("cli_render");
+    fs::create_dir_all(dir.join("notes/img")).unwrap();
+    fs::write(
+        dir.join("notes/Main.md"),
+        "Intro\n![[Guide#Setup]]\n![[diagram.png]]\n![[Loop]]\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("notes/Guide.md"),
+        "# Guide\n## Setup\nInstall it.\n",
+    )
+    .unwrap();
+    fs::write(dir.join("notes/Loop.md"), "loop ![[Main]]\n").unwrap();
+    fs::write(dir.join("notes/img/diagram.png"), [0x89, 0xff]).unwrap();
+    let run = |args: &[&str]| notes(&dir).args(args).output().unwrap();
+
+    let output = run(&["show", "Main"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\n![[Guide#Setup]]\n"));
+    let output = run(&["show", "--render", "Main"]);
+    assert!(output.status.success());
+    let shown = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        shown.starts_with(
+            "--- Main ---\nIntro\n## Setup\nInstall it.\n![diagram.png](img/diagram.png)\nloop ![[Main]]\n"
+        ),
+        "{}",
+        shown
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("embed cycle Main > Loop > Main"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run(&["export", "Main", "--max-depth", "0"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Intro\n![[Guide#Setup]]\n![diagram.png](notes/img/diagram.png)\n![[Loop]]\n"
+    );
+    let output = run(&["--quiet", "export", "Main", "-o", "out/Main.md"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    let exported = fs::read_to_string(dir.join("out/Main.md")).unwrap();
+    assert!(exported.contains("![diagram.png](../notes/img/diagram.png)\n"));
+    assert_eq!(run(&["export", "Nothing"]).status.code(), Some(66));
+}
+
+#[test]
+fn cli_recovers_rename_before_changes() {
+    let dir = TempDir::new("cli_recover");
+    fs::create_dir_all(dir.join("notes/.notes/rename")).unwrap();
+    fs::write(dir.join("notes/Index.md"), "Text\n").unwrap();
+    // staged contents of a rename that never started
+    fs::write(dir.join("notes/.notes/rename/0"), "x").unwrap();
+    let run = |args: &[&str]| {
+        notes(&dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+
+    for args in [&["list"][..], &["show", "Index"], &["search", "Text"]] {
+        assert_eq!(run(args), Some(0));
+        assert!(dir.join("notes/.notes/rename/0").exists());
+    }
+    assert_eq!(run(&["new", "Other"]), Some(0));
+    assert!(!dir.join("notes/.notes/rename").exists());
 }
@@ -1,20 +1,16 @@
-use notes_core::note::Note;
+mod common;
+
+use common::TempDir;
+use notes_core::Error;
+use notes_core::note::{NOTES_DIR, Note, file_stem_for_title, set_vault_dir};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
-
-fn temp_dir() -> PathBuf {
-    let mut dir = env::temp_dir();
-    dir.push(format!("notes_test_{}", std::process::id()));
-    let _ = fs::remove_dir_all(&dir);
-    fs::create_dir_all(&dir).unwrap();
-    dir
-}
+use std::io;
 
 #[test]
 fn save_and_load_note() {
-    let dir = temp_dir();
-    env::set_current_dir(&dir).unwrap();
+    let dir = TempDir::new("notes");
+    set_vault_dir(dir.join(NOTES_DIR));
 
     let note = Note::new("Test".to_string(), "content".to_string(), None);
     note.save().unwrap();
@@ -22,10 +18,74 @@ fn save_and_load_note() {
     let loaded = Note::load(&note.path).unwrap();
     assert_eq!(loaded.title, "Test");
     assert_eq!(loaded.content, "content");
+
+    let text = "---\naliases: [Exam, \"Quiz\"]\ncustom: kept # as is\n---\nbody\n";
+    fs::write(&note.path, text).unwrap();
+    let loaded = Note::load(&note.path).unwrap();
+    assert_eq!(loaded.aliases, vec!["Exam", "Quiz"]);
+    assert_eq!(loaded.content, "body\n");
+    loaded.save().unwrap();
+    assert_eq!(fs::read_to_string(&note.path).unwrap(), text);
+
+    let note = Note::new("AC/DC: Live?".to_string(), "body".to_string(), None);
+    assert!(note.path.ends_with("notes/AC-DC- Live-.md"));
+    note.save().unwrap();
+    let loaded = Note::load(&note.path).unwrap();
+    assert_eq!(loaded.title, "AC/DC: Live?");
+    assert_eq!(loaded.content, "body");
+
+    // "AC/DC" and "AC-DC" share a file name; the first note is kept
+    let path = Note::path_from_title("AC-DC");
+    fs::write(&path, "important").unwrap();
+    let note = Note::new("AC/DC".to_string(), "other".to_string(), None);
+    assert_eq!(note.path, path);
+    assert!(matches!(note.save(), Err(Error::NoteExists(p)) if p == path));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "important");
+}
+
+#[test]
+fn load_missing_note() {
+    let path = env::temp_dir().join(format!("missing_note_{}.md", std::process::id()));
+    assert!(matches!(Note::load(&path), Err(Error::NoteNotFound(p)) if p == path));
+}
+
+#[test]
+fn load_binary_note() {
+    let dir = TempDir::new("binary_note");
+    let path = dir.join("Image.md");
+    fs::write(&path, [0xff, 0xfe]).unwrap();
+    assert!(matches!(Note::load(&path), Err(Error::NotUtf8(p)) if p == path));
+
+    // other invalid data keeps its own message
+    let source = io::Error::new(io::ErrorKind::InvalidData, "bad index");
+    let err = Error::io(&path, source);
+    assert!(matches!(err, Error::Io { .. }));
+    assert!(err.to_string().ends_with("bad index"));
 }
 
 #[test]
 fn path_from_title() {
     let path = Note::path_from_title("My Note");
     assert!(path.ends_with("notes/My Note.md"));
+    let path = Note::path_from_title("foop.md");
+    assert!(path.ends_with("notes/foop.md"));
+}
+
+#[test]
+fn unsafe_titles() {
+    let stem = |t: &str| file_stem_for_title(t).unwrap();
+    assert_eq!(stem("../../etc/passwd"), "-..-etc-passwd");
+    assert_eq!(stem(".hidden"), "hidden");
+    assert_eq!(stem("notes notes! "), "notes notes!");
+    assert_eq!(stem("Report.MD"), "Report");
+    assert_eq!(stem("con"), "con_");
+    assert_eq!(stem("nul.txt"), "nul_.txt");
+    assert_eq!(stem("Com1 .tar.gz"), "Com1_ .tar.gz");
+    assert_eq!(stem(&"x".repeat(300)).len(), 200);
+    for bad in ["", "   ", "...", ". ."] {
+        assert!(matches!(
+            file_stem_for_title(bad),
+            Err(Error::InvalidTitle { .. })
+        ));
+    }
 }
@@ -1,3 +1,4 @@
+use chrono::{Datelike, NaiveDate};
 use gtk4::gdk;
 use gtk4::prelude::*;
 use gtk4::{
@@ -7,7 +8,14 @@ use gtk4::{
 use open;
 use vte4::{PtyFlags, Terminal, TerminalExtManual};
 
+use notes_core::config::{Config, DEFAULT_APP_ID, UserConfig, config, set_config};
+use notes_core::editor::{DEFAULT_TEMPLATE, Template, create_note, editor_argv, list_templates};
+use notes_core::graph::LinkKind;
 use notes_core::note::{set_vault_dir, vault_dir};
+use notes_core::periodic::{Period, find_periodic, open_periodic};
+use notes_core::rename::recover;
+use notes_core::tags::{TagNodes, tag_nodes};
+use notes_core::trash::trash_node;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::hash::Hasher;
@@ -55,19 +63,42 @@ fn lighten_color(r: f64, g: f64, b: f64) -> (f64, f64, f64) {
 }
 
 fn node_color(node: &notes_core::graph::Node) -> (f64, f64, f64) {
-    if node.is_directory() {
-        (1.0, 1.0, 1.0)
-    } else if let Some(ext) = node.primary_file_format() {
+    if let Some(ext) = node.primary_file_format() {
         hash_color(&ext)
     } else {
-        hash_color("md")
+        hash_color(&config().default_format)
+    }
+}
+
+/// A hidden label used to report errors next to the widget that caused them.
+fn error_label() -> Label {
+    let label = Label::new(None);
+    label.set_wrap(true);
+    label.add_css_class("error");
+    label.set_visible(false);
+    label
+}
+
+fn show_error(label: &Label, err: &notes_core::Error) {
+    label.set_text(&err.to_string());
+    label.set_visible(true);
+}
+
+fn show_result(label: &Label, result: notes_core::Result<()>) {
+    match result {
+        Ok(()) => label.set_visible(false),
+        Err(e) => show_error(label, &e),
     }
 }
 
 pub fn run_gui() {
-    let app = Application::builder()
-        .application_id("com.example.notes")
-        .build();
+    let app_id = UserConfig::load()
+        .map(|user| user.app_id)
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            DEFAULT_APP_ID.to_string()
+        });
+    let app = Application::builder().application_id(app_id).build();
 
     app.connect_activate(|app| {
         show_dashboard(app);
@@ -91,7 +122,16 @@ fn show_dashboard(app: &Application) {
     let entry = Entry::new();
     entry.set_hexpand(true);
     entry.set_placeholder_text(Some("Vault directory"));
+    // suggest the vault the CLI would use from here
+    if let Ok(location) = std::env::current_dir()
+        .map_err(|e| notes_core::Error::io(Path::new("."), e))
+        .and_then(|cwd| notes_core::vault::find_vault(None, &cwd))
+        && location.path.is_dir()
+    {
+        entry.set_text(&location.path.to_string_lossy());
+    }
     let button = Button::with_label("Open Vault");
+    let error_label = error_label();
     let vbox = Box::new(Orientation::Vertical, 5);
     vbox.set_margin_top(12);
     vbox.set_margin_bottom(12);
@@ -99,13 +139,30 @@ fn show_dashboard(app: &Application) {
     vbox.set_margin_end(12);
     vbox.append(&entry);
     vbox.append(&button);
+    vbox.append(&error_label);
     window.set_child(Some(&vbox));
 
     button.connect_clicked(
-        glib::clone!(@weak window, @weak app, @weak entry => move |_| {
+        glib::clone!(@weak window, @weak app, @weak entry, @weak error_label => move |_| {
             let path_str = entry.text();
             if !path_str.is_empty() {
                 let dir = expand_tilde(path_str.as_str());
+                if !dir.is_dir() {
+                    show_error(&error_label, &notes_core::Error::VaultNotFound(dir));
+                    return;
+                }
+                match Config::for_vault(&dir) {
+                    Ok((config, warnings)) => {
+                        for warning in warnings {
+                            eprintln!("Warning: {}", warning);
+                        }
+                        set_config(config);
+                    }
+                    Err(e) => {
+                        show_error(&error_label, &e);
+                        return;
+                    }
+                }
                 set_vault_dir(dir);
                 window.close();
                 open_main_window(&app);
@@ -116,6 +173,10 @@ fn show_dashboard(app: &Application) {
 }
 
 fn open_main_window(app: &Application) {
+    // the graph view changes the vault, so a rename cut short is finished first
+    if let Err(e) = recover(&vault_dir()) {
+        eprintln!("Warning: {}", e);
+    }
     let notebook = Notebook::new();
     notebook.set_hexpand(true);
     notebook.set_vexpand(true);
@@ -218,10 +279,12 @@ fn open_any_path(
         let term = Terminal::new();
         term.set_hexpand(true);
         term.set_vexpand(true);
+        let argv = editor_argv(path);
+        let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
         term.spawn_async(
             PtyFlags::DEFAULT,
             None::<&str>,
-            &["nvim", &key],
+            &argv,
             &[],
             glib::SpawnFlags::SEARCH_PATH,
             || {},
@@ -293,7 +356,10 @@ fn open_graph_tab(
     graph_tab: &Rc<RefCell<Option<Overlay>>>,
     graph_cb: &Rc<RefCell<Option<std::boxed::Box<dyn Fn(String)>>>>,
 ) {
-    use notes_core::graph::{load_graph_data, update_open_notes};
+    use notes_core::graph::{
+        Changes, GraphData, load_graph_data, refresh, update_open_notes, update_paths,
+    };
+    use notes_core::watch::VaultWatcher;
     use std::f64::consts::PI;
 
     if let Some(ref existing) = *graph_tab.borrow() {
@@ -312,10 +378,15 @@ fn open_graph_tab(
         pan_y: f64,
         scale: f64,
         hover: Option<usize>,
+        /// Whether tags are shown as extra nodes
+        show_tags: bool,
+        tags: TagNodes,
+        tag_positions: Vec<(f64, f64)>,
+        tag_velocities: Vec<(f64, f64)>,
     }
 
-    fn reset_state(state: &mut GraphState) {
-        state.data = load_graph_data();
+    fn reset_state(state: &mut GraphState) -> notes_core::Result<()> {
+        state.data = load_graph_data()?;
         let n = state.data.graph.nodes.len();
         state.positions.clear();
         state.colors.clear();
@@ -330,21 +401,74 @@ fn open_graph_tab(
         state.pan_y = 0.0;
         state.scale = 1.0;
         state.hover = None;
+        state.tags = TagNodes::default();
+        sync_tags(state);
+        Ok(())
     }
 
-    fn add_node_to_state(state: &mut GraphState, _title: &str) {
-        let new_data = load_graph_data();
+    /// Bring the tag nodes in line with the notes' tags. Tags that are still
+    /// there keep their place; new ones start next to a note using them.
+    fn sync_tags(state: &mut GraphState) {
+        let old: HashMap<String, usize> = state
+            .tags
+            .tags
+            .iter()
+            .enumerate()
+            .map(|(k, tag)| (tag.clone(), k))
+            .collect();
+        let tags = tag_nodes(&state.data.graph);
+        let mut positions = Vec::new();
+        let mut velocities = Vec::new();
+        for (k, tag) in tags.tags.iter().enumerate() {
+            if let Some(&j) = old.get(tag) {
+                positions.push(state.tag_positions[j]);
+                velocities.push(state.tag_velocities[j]);
+                continue;
+            }
+            let (x, y) = tags
+                .notes
+                .iter()
+                .find(|&&(_, t)| t == k)
+                .map(|&(i, _)| state.positions[i])
+                .unwrap_or((0.0, 0.0));
+            positions.push((x + 10.0, y + 10.0));
+            velocities.push((0.0, 0.0));
+        }
+        state.tags = tags;
+        state.tag_positions = positions;
+        state.tag_velocities = velocities;
+    }
+
+    fn add_node_to_state(state: &mut GraphState, _title: &str) -> notes_core::Result<()> {
+        if state.data.graph.nodes.is_empty() {
+            // nothing laid out yet, or the first load failed
+            return reset_state(state);
+        }
+        update_state(state, refresh)
+    }
+
+    /// Apply an incremental update to the graph data, keeping the layout of
+    /// nodes that are still there.
+    fn update_state(
+        state: &mut GraphState,
+        update: impl FnOnce(&mut GraphData) -> notes_core::Result<Changes>,
+    ) -> notes_core::Result<()> {
+        let old_index: HashMap<PathBuf, usize> = state
+            .data
+            .graph
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.rel_path.clone(), i))
+            .collect();
+        if update(&mut state.data)?.is_empty() {
+            return Ok(());
+        }
         let mut new_positions = Vec::new();
         let mut new_velocities = Vec::new();
         let mut new_colors = Vec::new();
-        for node in &new_data.graph.nodes {
-            if let Some(idx) = state
-                .data
-                .graph
-                .nodes
-                .iter()
-                .position(|n| n.name == node.name)
-            {
+        for node in &state.data.graph.nodes {
+            if let Some(&idx) = old_index.get(&node.rel_path) {
                 new_positions.push(state.positions[idx]);
                 new_velocities.push(state.velocities[idx]);
             } else {
@@ -353,15 +477,23 @@ fn open_graph_tab(
             }
             new_colors.push(node_color(node));
         }
-        state.data = new_data;
         state.positions = new_positions;
         state.velocities = new_velocities;
         state.colors = new_colors;
         state.hover = None;
+        sync_tags(state);
+        Ok(())
     }
 
+    let status = error_label();
+    status.set_halign(gtk4::Align::Center);
+    status.set_valign(gtk4::Align::Start);
+
     let mut init = GraphState {
-        data: load_graph_data(),
+        data: load_graph_data().unwrap_or_else(|e| {
+            show_error(&status, &e);
+            GraphData::default()
+        }),
         positions: Vec::new(),
         velocities: Vec::new(),
         colors: Vec::new(),
@@ -369,7 +501,14 @@ fn open_graph_tab(
         pan_y: 0.0,
         scale: 1.0,
         hover: None,
+        show_tags: false,
+        tags: TagNodes::default(),
+        tag_positions: Vec::new(),
+        tag_velocities: Vec::new(),
     };
+    for error in &init.data.skipped {
+        eprintln!("Warning: {}; the notes in it are left out", error);
+    }
     let n = init.data.graph.nodes.len();
     for i in 0..n {
         let angle = i as f64 / n.max(1) as f64 * 2.0 * PI;
@@ -378,6 +517,7 @@ fn open_graph_tab(
         init.colors.push(node_color(&init.data.graph.nodes[i]));
     }
     init.velocities = vec![(0.0, 0.0); n];
+    sync_tags(&mut init);
     let state = Rc::new(RefCell::new(init));
 
     let area = DrawingArea::new();
@@ -386,9 +526,10 @@ fn open_graph_tab(
 
     let cb_state = state.clone();
     let cb_area = area.clone();
+    let cb_status = status.clone();
     *graph_cb.borrow_mut() = Some(std::boxed::Box::new(move |title: String| {
         let mut st = cb_state.borrow_mut();
-        add_node_to_state(&mut st, &title);
+        show_result(&cb_status, add_node_to_state(&mut st, &title));
         cb_area.queue_draw();
     }));
 
@@ -396,6 +537,7 @@ fn open_graph_tab(
     container.set_hexpand(true);
     container.set_vexpand(true);
     container.set_child(Some(&area));
+    container.add_overlay(&status);
 
     let button_box = Box::new(Orientation::Vertical, 5);
     button_box.set_halign(gtk4::Align::Start);
@@ -407,15 +549,24 @@ fn open_graph_tab(
     let new_button = Button::new();
     new_button.set_child(Some(&Image::from_icon_name("document-new-symbolic")));
     new_button.set_size_request(40, 40);
+    let calendar_button = Button::new();
+    calendar_button.set_child(Some(&Image::from_icon_name("x-office-calendar-symbolic")));
+    calendar_button.set_size_request(40, 40);
     button_box.append(&home_button);
     button_box.append(&new_button);
+    let tags_button = gtk4::ToggleButton::with_label("#");
+    tags_button.set_tooltip_text(Some("Show tags"));
+    tags_button.set_size_request(40, 40);
+    button_box.append(&calendar_button);
+    button_box.append(&tags_button);
     container.add_overlay(&button_box);
 
     let home_state = state.clone();
     let home_area = area.clone();
+    let home_status = status.clone();
     home_button.connect_clicked(move |_| {
         let mut st = home_state.borrow_mut();
-        reset_state(&mut st);
+        show_result(&home_status, reset_state(&mut st));
         home_area.queue_draw();
     });
 
@@ -427,9 +578,13 @@ fn open_graph_tab(
         pop.set_autohide(true);
         let entry = Entry::new();
         let create_btn = Button::with_label("Create");
+        let error = error_label();
+        let picker = TemplatePicker::new(&error);
         let v = Box::new(Orientation::Vertical, 5);
         v.append(&entry);
+        v.append(&picker.widget);
         v.append(&create_btn);
+        v.append(&error);
         pop.set_child(Some(&v));
         pop.set_parent(btn);
         pop.popup();
@@ -440,7 +595,10 @@ fn open_graph_tab(
         let do_create = Rc::new(move || {
             let title = entry_clone.text().to_string();
             if !title.is_empty() {
-                create_new_note(&title);
+                if let Err(e) = create_new_note(&title, &picker) {
+                    show_error(&error, &e);
+                    return;
+                }
                 if let Some(cb) = &*cb_inner.borrow() {
                     cb(title.clone());
                 }
@@ -456,6 +614,70 @@ fn open_graph_tab(
         });
     });
 
+    let tags_state = state.clone();
+    let tags_area = area.clone();
+    tags_button.connect_toggled(move |button| {
+        tags_state.borrow_mut().show_tags = button.is_active();
+        tags_area.queue_draw();
+    });
+
+    // Jump to the daily note of a day, creating it if needed
+    let day_state = state.clone();
+    let day_area = area.clone();
+    let day_status = status.clone();
+    let day_notebook = notebook.clone();
+    let day_tabs = open_tabs.clone();
+    calendar_button.connect_clicked(move |btn| {
+        let pop = Popover::new();
+        pop.set_has_arrow(true);
+        pop.set_position(PositionType::Top);
+        pop.set_autohide(true);
+        let calendar = gtk4::Calendar::new();
+        mark_daily_notes(&calendar);
+        calendar.connect_next_month(mark_daily_notes);
+        calendar.connect_prev_month(mark_daily_notes);
+        calendar.connect_next_year(mark_daily_notes);
+        calendar.connect_prev_year(mark_daily_notes);
+        let error = error_label();
+        let v = Box::new(Orientation::Vertical, 5);
+        v.append(&calendar);
+        v.append(&error);
+        pop.set_child(Some(&v));
+        pop.set_parent(btn);
+        pop.popup();
+
+        let pop_clone = pop.clone();
+        let state = day_state.clone();
+        let area = day_area.clone();
+        let status = day_status.clone();
+        let notebook = day_notebook.clone();
+        let open_tabs = day_tabs.clone();
+        calendar.connect_day_selected(move |calendar| {
+            let Some(date) = calendar_date(calendar) else {
+                return;
+            };
+            let (path, created) = match open_periodic(Period::Daily, date) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    show_error(&error, &e);
+                    return;
+                }
+            };
+            pop_clone.popdown();
+            let mut st = state.borrow_mut();
+            if created && let Err(e) = update_state(&mut st, refresh) {
+                show_error(&status, &e);
+            }
+            let nodes = &st.data.graph.nodes;
+            if let Some(node) = nodes.iter().find(|n| n.paths.contains(&path)).cloned() {
+                open_any_path(&notebook, &open_tabs, &node, &path);
+                let result = update_state(&mut st, |data| update_open_notes(data, &[]));
+                show_result(&status, result);
+            }
+            area.queue_draw();
+        });
+    });
+
     let draw_state = state.clone();
     area.set_draw_func(move |_, ctx, width, height| {
         let st = draw_state.borrow();
@@ -475,10 +697,14 @@ fn open_graph_tab(
         let pan_y = st.pan_y + height as f64 / 2.0;
 
         ctx.set_line_width(1.0);
-        ctx.set_source_rgb(0.6, 0.6, 0.6);
-        for &(from, to) in &graph.edges {
-            let (sx, sy) = positions[from];
-            let (tx, ty) = positions[to];
+        for edge in &graph.edges {
+            // explicit links are drawn darker than implicit name matches
+            match edge.kind {
+                LinkKind::Explicit => ctx.set_source_rgb(0.3, 0.3, 0.3),
+                LinkKind::Implicit => ctx.set_source_rgb(0.6, 0.6, 0.6),
+            }
+            let (sx, sy) = positions[edge.from];
+            let (tx, ty) = positions[edge.to];
             let sx = sx * scale + pan_x;
             let sy = sy * scale + pan_y;
             let tx = tx * scale + pan_x;
@@ -488,6 +714,39 @@ fn open_graph_tab(
             let _ = ctx.stroke();
         }
 
+        // tags are drawn as dashed links to small squares
+        if st.show_tags {
+            let tag_links = st.tags.notes.iter().map(|&(i, k)| (positions[i], k));
+            let nested = st
+                .tags
+                .nested
+                .iter()
+                .map(|&(k, parent)| (st.tag_positions[parent], k));
+            ctx.set_source_rgb(0.5, 0.6, 0.8);
+            ctx.set_dash(&[4.0, 4.0], 0.0);
+            for ((sx, sy), k) in tag_links.chain(nested) {
+                let (tx, ty) = st.tag_positions[k];
+                ctx.move_to(sx * scale + pan_x, sy * scale + pan_y);
+                ctx.line_to(tx * scale + pan_x, ty * scale + pan_y);
+                let _ = ctx.stroke();
+            }
+            ctx.set_dash(&[], 0.0);
+            for (k, tag) in st.tags.tags.iter().enumerate() {
+                let (x, y) = st.tag_positions[k];
+                let sx = x * scale + pan_x;
+                let sy = y * scale + pan_y;
+                let half = 5.0 * scale.max(0.2);
+                ctx.rectangle(sx - half, sy - half, 2.0 * half, 2.0 * half);
+                ctx.set_source_rgb(0.5, 0.6, 0.8);
+                let _ = ctx.fill();
+                let name = tag.rsplit('/').next().unwrap_or(tag);
+                ctx.move_to(sx + half + 6.0, sy + 4.0);
+                ctx.set_source_rgb(0.2, 0.3, 0.5);
+                let _ = ctx.show_text(&format!("#{}", name));
+                ctx.new_path();
+            }
+        }
+
         let text_alpha = ((scale - 0.4) * 5.0).clamp(0.0, 1.0);
         let show_names = text_alpha > 0.0 && graph.nodes.len() < 50;
 
@@ -497,33 +756,16 @@ fn open_graph_tab(
             let sy = y * scale + pan_y;
             let radius = 8.0 + (node.links as f64).sqrt() * 2.0;
             let (r, g, b) = st.colors.get(i).copied().unwrap_or((0.2, 0.6, 0.86));
-            if node.is_directory() {
-                let fill = if st.hover == Some(i) {
-                    (0.9, 0.9, 0.9)
-                } else {
-                    (1.0, 1.0, 1.0)
-                };
-                ctx.arc(sx, sy, radius * scale.max(0.2), 0.0, 2.0 * PI);
-                ctx.set_source_rgb(fill.0, fill.1, fill.2);
-                let _ = ctx.fill_preserve();
-                ctx.set_source_rgb(0.0, 0.0, 0.0);
-                let _ = ctx.stroke();
-                ctx.arc(sx, sy, radius * scale.max(0.2) * 0.4, 0.0, 2.0 * PI);
-                ctx.set_source_rgb(0.0, 0.0, 0.0);
-                let _ = ctx.fill();
-                ctx.new_path();
+            ctx.arc(sx, sy, radius * scale.max(0.2), 0.0, 2.0 * PI);
+            if st.hover == Some(i) {
+                let (lr, lg, lb) = lighten_color(r, g, b);
+                ctx.set_source_rgb(lr, lg, lb);
             } else {
-                ctx.arc(sx, sy, radius * scale.max(0.2), 0.0, 2.0 * PI);
-                if st.hover == Some(i) {
-                    let (lr, lg, lb) = lighten_color(r, g, b);
-                    ctx.set_source_rgb(lr, lg, lb);
-                } else {
-                    ctx.set_source_rgb(r, g, b);
-                }
-                let _ = ctx.fill_preserve();
-                ctx.set_source_rgb(0.0, 0.0, 0.0);
-                let _ = ctx.stroke();
+                ctx.set_source_rgb(r, g, b);
             }
+            let _ = ctx.fill_preserve();
+            ctx.set_source_rgb(0.0, 0.0, 0.0);
+            let _ = ctx.stroke();
 
             let label_alpha = if st.hover == Some(i) { 1.0 } else { text_alpha };
             if st.hover == Some(i) || show_names {
@@ -624,6 +866,7 @@ fn open_graph_tab(
     let click_area = area.clone();
     let notebook_clone = notebook.clone();
     let tabs_clone = open_tabs.clone();
+    let click_status = status.clone();
     let click = gtk4::GestureClick::new();
     click.connect_released(move |_, _n, x, y| {
         let note_name_opt = {
@@ -638,7 +881,7 @@ fn open_graph_tab(
                 let radius = 8.0 + (node.links as f64).sqrt() * 2.0;
                 let dist2 = (gx - nx).powi(2) + (gy - ny).powi(2);
                 if dist2 <= radius.powi(2) {
-                    res = Some(node.name.clone());
+                    res = Some(node.rel_path.clone());
                     break;
                 }
             }
@@ -650,67 +893,150 @@ fn open_graph_tab(
         };
         let idx_opt = {
             let st = click_state.borrow();
-            st.data.graph.nodes.iter().position(|n| n.name == note_name)
+            st.data
+                .graph
+                .nodes
+                .iter()
+                .position(|n| n.rel_path == note_name)
         };
 
         let mut chosen = None;
+        let mut created = Ok(());
         if let Some(idx) = idx_opt {
             let mut st = click_state.borrow_mut();
             let node = &mut st.data.graph.nodes[idx];
-            if let Some(md) = node
-                .paths
-                .iter()
-                .find(|p| p.extension().and_then(|e| e.to_str()) == Some("md"))
-                .cloned()
-            {
-                chosen = Some((node.clone(), md));
+            if let Some(path) = node.text_file().cloned() {
+                chosen = Some((node.clone(), path));
             } else {
-                let mut text_paths: Vec<PathBuf> = node
-                    .paths
-                    .iter()
-                    .filter(|p| is_text_file(p))
-                    .cloned()
-                    .collect();
-                text_paths.sort_by_key(|p| {
-                    p.extension()
-                        .and_then(|s| s.to_str())
-                        .map(|s| s.to_owned())
-                        .unwrap_or_default()
-                });
-                if let Some(p) = text_paths.first() {
-                    chosen = Some((node.clone(), p.clone()));
-                } else {
-                    let new_path = vault_dir().join(format!("{}.md", node.name));
-                    let _ = std::fs::File::create(&new_path);
+                let file_name = format!("{}.{}", node.name, config().default_format);
+                let new_path = vault_dir().join(node.folder()).join(file_name);
+                created = std::fs::File::create(&new_path)
+                    .map(|_| ())
+                    .map_err(|e| notes_core::Error::io(&new_path, e));
+                if created.is_ok() {
                     node.paths.push(new_path.clone());
                     chosen = Some((node.clone(), new_path));
                 }
             }
         }
+        if let Err(e) = created {
+            show_error(&click_status, &e);
+            return;
+        }
         if let Some((node, path)) = chosen {
             open_any_path(&notebook_clone, &tabs_clone, &node, &path);
             let mut st = click_state.borrow_mut();
-            notes_core::graph::update_open_notes(&mut st.data, &[]);
+            show_result(
+                &click_status,
+                update_state(&mut st, |data| update_open_notes(data, &[])),
+            );
             click_area.queue_draw();
         }
     });
     area.add_controller(click);
 
+    // Context menu of a node
+    let menu_state = state.clone();
+    let menu_area = area.clone();
+    let menu_status = status.clone();
+    let context = gtk4::GestureClick::new();
+    context.set_button(gdk::BUTTON_SECONDARY);
+    context.connect_pressed(move |_, _n, x, y| {
+        let (rel_path, mut linked) = {
+            let st = menu_state.borrow();
+            let pan_x = st.pan_x + menu_area.width() as f64 / 2.0;
+            let pan_y = st.pan_y + menu_area.height() as f64 / 2.0;
+            let gx = (x - pan_x) / st.scale;
+            let gy = (y - pan_y) / st.scale;
+            let graph = &st.data.graph;
+            let hit = (0..graph.nodes.len()).find(|&i| {
+                let (nx, ny) = st.positions[i];
+                let radius = 8.0 + (graph.nodes[i].links as f64).sqrt() * 2.0;
+                (gx - nx).powi(2) + (gy - ny).powi(2) <= radius.powi(2)
+            });
+            let Some(i) = hit else {
+                return;
+            };
+            let linked: Vec<String> = graph
+                .incoming(i)
+                .map(|edge| graph.nodes[edge.from].name.clone())
+                .collect();
+            (graph.nodes[i].rel_path.clone(), linked)
+        };
+        linked.dedup();
+
+        let pop = Popover::new();
+        pop.set_has_arrow(true);
+        pop.set_autohide(true);
+        let v = Box::new(Orientation::Vertical, 5);
+        if !linked.is_empty() {
+            let text = format!("Links from {} will break", linked.join(", "));
+            let warning = Label::new(Some(&text));
+            warning.set_wrap(true);
+            warning.set_max_width_chars(40);
+            v.append(&warning);
+        }
+        let trash_button = Button::with_label("Move to Trash");
+        v.append(&trash_button);
+        pop.set_child(Some(&v));
+        pop.set_parent(&menu_area);
+        pop.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+        pop.popup();
+
+        let pop_clone = pop.clone();
+        let trash_state = menu_state.clone();
+        let trash_area = menu_area.clone();
+        let trash_status = menu_status.clone();
+        trash_button.connect_clicked(move |_| {
+            pop_clone.popdown();
+            let mut st = trash_state.borrow_mut();
+            let nodes = &st.data.graph.nodes;
+            let Some(i) = nodes.iter().position(|n| n.rel_path == rel_path) else {
+                return;
+            };
+            let result = trash_node(&st.data, i).and_then(|_| update_state(&mut st, refresh));
+            show_result(&trash_status, result);
+            trash_area.queue_draw();
+        });
+    });
+    area.add_controller(context);
+
     // simple physics update
     let sim_area = area.clone();
     let sim_state = state.clone();
-    glib::timeout_add_local(std::time::Duration::from_millis(16), move || {
+    let physics = config().graph;
+    let tick = std::time::Duration::from_millis(physics.tick_ms);
+    glib::timeout_add_local(tick, move || {
         {
             let mut st = sim_state.borrow_mut();
-            let n = st.data.graph.nodes.len();
+            let st = &mut *st;
+            // shown tags take part as nodes after the notes
+            let notes = st.data.graph.nodes.len();
+            let tags = if st.show_tags { st.tags.tags.len() } else { 0 };
+            let n = notes + tags;
+            let mut positions: Vec<(f64, f64)> = st.positions.clone();
+            positions.extend(&st.tag_positions[..tags]);
+            let mut velocities: Vec<(f64, f64)> = st.velocities.clone();
+            velocities.extend(&st.tag_velocities[..tags]);
+            let mut springs: Vec<(usize, usize)> = st
+                .data
+                .graph
+                .edges
+                .iter()
+                .map(|edge| (edge.from, edge.to))
+                .collect();
+            if st.show_tags {
+                springs.extend(st.tags.notes.iter().map(|&(i, k)| (i, notes + k)));
+                springs.extend(st.tags.nested.iter().map(|&(k, j)| (notes + k, notes + j)));
+            }
             let mut forces = vec![(0.0, 0.0); n];
             for i in 0..n {
                 for j in (i + 1)..n {
-                    let dx = st.positions[i].0 - st.positions[j].0;
-                    let dy = st.positions[i].1 - st.positions[j].1;
+                    let dx = positions[i].0 - positions[j].0;
+                    let dy = positions[i].1 - positions[j].1;
                     let dist2 = dx * dx + dy * dy + 0.01;
                     let dist = dist2.sqrt();
-                    let rep = 2000.0 / dist2;
+                    let rep = physics.repulsion / dist2;
                     let fx = dx / dist * rep;
                     let fy = dy / dist * rep;
                     forces[i].0 += fx;
@@ -719,11 +1045,11 @@ fn open_graph_tab(
                     forces[j].1 -= fy;
                 }
             }
-            for &(a, b) in &st.data.graph.edges {
-                let dx = st.positions[a].0 - st.positions[b].0;
-                let dy = st.positions[a].1 - st.positions[b].1;
+            for &(a, b) in &springs {
+                let dx = positions[a].0 - positions[b].0;
+                let dy = positions[a].1 - positions[b].1;
                 let dist = (dx * dx + dy * dy).sqrt();
-                let spring = 0.01 * (dist - 100.0);
+                let spring = physics.spring * (dist - physics.spring_length);
                 let fx = dx / dist * spring;
                 let fy = dy / dist * spring;
                 forces[a].0 -= fx;
@@ -732,32 +1058,67 @@ fn open_graph_tab(
                 forces[b].1 += fy;
             }
             for i in 0..n {
-                st.velocities[i].0 = (st.velocities[i].0 + forces[i].0) * 0.85;
-                st.velocities[i].1 = (st.velocities[i].1 + forces[i].1) * 0.85;
-                if st.velocities[i].0.abs() < 0.001 && forces[i].0.abs() < 0.001 {
-                    st.velocities[i].0 *= 0.5;
+                velocities[i].0 = (velocities[i].0 + forces[i].0) * physics.damping;
+                velocities[i].1 = (velocities[i].1 + forces[i].1) * physics.damping;
+                if velocities[i].0.abs() < 0.001 && forces[i].0.abs() < 0.001 {
+                    velocities[i].0 *= 0.5;
                 }
-                if st.velocities[i].1.abs() < 0.001 && forces[i].1.abs() < 0.001 {
-                    st.velocities[i].1 *= 0.5;
+                if velocities[i].1.abs() < 0.001 && forces[i].1.abs() < 0.001 {
+                    velocities[i].1 *= 0.5;
                 }
-                st.positions[i].0 += st.velocities[i].0 * 0.1;
-                st.positions[i].1 += st.velocities[i].1 * 0.1;
+                positions[i].0 += velocities[i].0 * physics.step;
+                positions[i].1 += velocities[i].1 * physics.step;
             }
+            st.tag_positions[..tags].copy_from_slice(&positions[notes..]);
+            st.tag_velocities[..tags].copy_from_slice(&velocities[notes..]);
+            positions.truncate(notes);
+            velocities.truncate(notes);
+            st.positions = positions;
+            st.velocities = velocities;
         }
         sim_area.queue_draw();
         glib::ControlFlow::Continue
     });
 
+    // follow changes made outside the app, e.g. in nvim or by git
+    match VaultWatcher::new(&vault_dir()) {
+        Ok(watcher) => {
+            let batches = watcher.subscribe();
+            let watch_state = state.clone();
+            let watch_area = area.clone();
+            let watch_status = status.clone();
+            glib::timeout_add_local(std::time::Duration::from_millis(250), move || {
+                // the graph tab is never closed, so neither is the watcher
+                let _ = &watcher;
+                let paths: Vec<PathBuf> = batches.try_iter().flatten().collect();
+                if !paths.is_empty() {
+                    let mut st = watch_state.borrow_mut();
+                    show_result(
+                        &watch_status,
+                        update_state(&mut st, |data| update_paths(data, &paths)),
+                    );
+                    watch_area.queue_draw();
+                }
+                glib::ControlFlow::Continue
+            });
+        }
+        Err(e) => show_error(&status, &e),
+    }
+
     let switch_state = state.clone();
     let switch_tabs = open_tabs.clone();
     let switch_area = area.clone();
     let switch_container = container.clone();
+    let switch_status = status.clone();
     notebook.connect_switch_page(move |nb, _page, idx| {
         if let Some(page_num) = nb.page_num(&switch_container) {
             if page_num == idx {
                 let titles: Vec<String> = switch_tabs.borrow().keys().cloned().collect();
                 let mut st = switch_state.borrow_mut();
-                update_open_notes(&mut st.data, &titles);
+                show_result(
+                    &switch_status,
+                    update_state(&mut st, |data| update_open_notes(data, &titles)),
+                );
                 switch_area.queue_draw();
             }
         }
@@ -772,9 +1133,101 @@ fn open_graph_tab(
     *graph_tab.borrow_mut() = Some(container);
 }
 
-fn create_new_note(title: &str) {
-    let note = notes_core::note::Note::new(title.to_string(), String::new(), None);
-    let _ = note.save();
+/// Choice of template in a new-note popover, with an entry for each of the
+/// chosen template's variables.
+struct TemplatePicker {
+    widget: Box,
+    template: Rc<RefCell<Option<Template>>>,
+    fields: Rc<RefCell<Vec<(String, Entry)>>>,
+}
+
+impl TemplatePicker {
+    /// A picker preselecting the default template. Templates that cannot be
+    /// read are reported in `error`.
+    fn new(error: &Label) -> Self {
+        let names = list_templates().unwrap_or_else(|e| {
+            show_error(error, &e);
+            Vec::new()
+        });
+        let mut items = vec!["No template"];
+        items.extend(names.iter().map(String::as_str));
+        let dropdown = gtk4::DropDown::from_strings(&items);
+        if let Some(k) = names.iter().position(|name| name == DEFAULT_TEMPLATE) {
+            dropdown.set_selected(k as u32 + 1);
+        }
+        let variables = Box::new(Orientation::Vertical, 5);
+        let widget = Box::new(Orientation::Vertical, 5);
+        widget.append(&dropdown);
+        widget.append(&variables);
+        widget.set_visible(!names.is_empty());
+        let picker = TemplatePicker {
+            widget,
+            template: Rc::new(RefCell::new(None)),
+            fields: Rc::new(RefCell::new(Vec::new())),
+        };
+
+        let template = picker.template.clone();
+        let fields = picker.fields.clone();
+        let error = error.clone();
+        let select = move |dropdown: &gtk4::DropDown| {
+            while let Some(child) = variables.first_child() {
+                variables.remove(&child);
+            }
+            fields.borrow_mut().clear();
+            error.set_visible(false);
+            let chosen = match dropdown.selected() {
+                0 => None,
+                k => names.get(k as usize - 1),
+            };
+            let loaded = chosen.map(|name| Template::load(name)).transpose();
+            let loaded = loaded.unwrap_or_else(|e| {
+                show_error(&error, &e);
+                None
+            });
+            for name in loaded.iter().flat_map(|t| t.variables()) {
+                let entry = Entry::new();
+                entry.set_placeholder_text(Some(&name));
+                variables.append(&entry);
+                fields.borrow_mut().push((name, entry));
+            }
+            *template.borrow_mut() = loaded;
+        };
+        select(&dropdown);
+        dropdown.connect_selected_notify(select);
+        picker
+    }
+}
+
+/// The day selected in `calendar`.
+fn calendar_date(calendar: &gtk4::Calendar) -> Option<NaiveDate> {
+    let date = calendar.date();
+    NaiveDate::from_ymd_opt(date.year(), date.month() as u32, date.day_of_month() as u32)
+}
+
+/// Mark the days of the month shown in `calendar` that have a daily note.
+fn mark_daily_notes(calendar: &gtk4::Calendar) {
+    calendar.clear_marks();
+    let Some(first) = calendar_date(calendar).and_then(|date| date.with_day(1)) else {
+        return;
+    };
+    let month = first
+        .iter_days()
+        .take_while(|day| day.month() == first.month());
+    for day in month {
+        if let Ok(Some(_)) = find_periodic(Period::Daily, day) {
+            calendar.mark_day(day.day());
+        }
+    }
+}
+
+fn create_new_note(title: &str, picker: &TemplatePicker) -> notes_core::Result<()> {
+    let values: HashMap<String, String> = picker
+        .fields
+        .borrow()
+        .iter()
+        .map(|(name, entry)| (name.clone(), entry.text().to_string()))
+        .collect();
+    create_note(title, picker.template.borrow().as_ref(), &values).map(|_| ())
 }
 
 fn show_new_note_popover(
@@ -786,9 +1239,13 @@ fn show_new_note_popover(
     pop.set_autohide(true);
     let entry = Entry::new();
     let create_btn = Button::with_label("Create");
+    let error = error_label();
+    let picker = TemplatePicker::new(&error);
     let v = Box::new(Orientation::Vertical, 5);
     v.append(&entry);
+    v.append(&picker.widget);
     v.append(&create_btn);
+    v.append(&error);
     pop.set_child(Some(&v));
     pop.set_parent(window);
     let rect = gdk::Rectangle::new(
@@ -806,7 +1263,10 @@ fn show_new_note_popover(
     let do_create = Rc::new(move || {
         let title = entry_clone.text().to_string();
         if !title.is_empty() {
-            create_new_note(&title);
+            if let Err(e) = create_new_note(&title, &picker) {
+                show_error(&error, &e);
+                return;
+            }
             if let Some(cb) = &*cb_clone.borrow() {
                 cb(title.clone());
             }